@@ -0,0 +1,444 @@
+use crate::{
+    camera::PerspectiveCamera,
+    degress_to_radians,
+    vec3::{Point3, Vec3},
+    PI,
+};
+use std::ops::{Add, Mul, Sub};
+
+/// Represents how a track computes the values between its keyframes
+#[derive(Clone, Copy)]
+pub enum Interpolation {
+    /// Straight lines between the keyframes
+    Linear,
+    /// A smooth Catmull-Rom spline that passes through every keyframe
+    Spline,
+}
+
+/// A value that can be keyframed, anything that can be added and scaled
+pub trait Keyable:
+    Copy + Add<Output = Self> + Sub<Output = Self> + Mul<f64, Output = Self>
+{
+}
+
+impl<T> Keyable for T where T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f64, Output = T> {}
+
+/// Represents the keyframes of a value over time
+///
+/// Before the first keyframe and after the last one the value stays the same.
+#[derive(Clone)]
+pub struct Track<T: Keyable> {
+    /// Keyframes as (time in seconds, value), sorted by time
+    keys: Vec<(f64, T)>,
+    /// How the values between the keyframes are computed
+    interpolation: Interpolation,
+}
+
+impl<T: Keyable> Track<T> {
+    /// Returns a track with its first keyframe, a track always has at least one
+    ///
+    /// # Arguments
+    ///
+    /// * interpolation - How the values between the keyframes are computed
+    /// * time - Time of the keyframe in seconds
+    /// * value - Value at that time
+    pub fn new(interpolation: Interpolation, time: f64, value: T) -> Self {
+        Self {
+            keys: vec![(time, value)],
+            interpolation,
+        }
+    }
+
+    /// Returns a track that always has the same value
+    pub fn constant(value: T) -> Self {
+        Self::new(Interpolation::Linear, 0.0, value)
+    }
+
+    /// Returns the track with a new keyframe, replacing the one at the same time if it exists
+    ///
+    /// # Arguments
+    ///
+    /// * time - Time of the keyframe in seconds
+    /// * value - Value at that time
+    pub fn key(mut self, time: f64, value: T) -> Self {
+        match self.keys.iter().position(|(t, _)| *t >= time) {
+            Some(i) if self.keys[i].0 == time => self.keys[i].1 = value,
+            Some(i) => self.keys.insert(i, (time, value)),
+            None => self.keys.push((time, value)),
+        }
+        self
+    }
+
+    /// Returns the value of the track at the given time
+    ///
+    /// # Arguments
+    ///
+    /// * time - Time in seconds
+    pub fn value_at(&self, time: f64) -> T {
+        let last = self.keys.len() - 1;
+        if time <= self.keys[0].0 {
+            return self.keys[0].1;
+        }
+        if time >= self.keys[last].0 {
+            return self.keys[last].1;
+        }
+
+        let i = self.keys.iter().rposition(|(t, _)| *t <= time).unwrap_or(0);
+        let (t1, p1) = self.keys[i];
+        let (t2, p2) = self.keys[i + 1];
+        let s = (time - t1) / (t2 - t1);
+
+        match self.interpolation {
+            Interpolation::Linear => p1 + (p2 - p1) * s,
+            Interpolation::Spline => {
+                // The missing neighbours at the ends are mirrored so the tangent is the segment's
+                let p0 = if i > 0 {
+                    self.keys[i - 1].1
+                } else {
+                    p1 + p1 - p2
+                };
+                let p3 = if i + 2 <= last {
+                    self.keys[i + 2].1
+                } else {
+                    p2 + p2 - p1
+                };
+                (p1 * 2.0
+                    + (p2 - p0) * s
+                    + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * (s * s)
+                    + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * (s * s * s))
+                    * 0.5
+            }
+        }
+    }
+}
+
+/// Represents a perspective camera whose position, target and field of view are keyframed
+pub struct AnimatedCamera {
+    /// The camera that is moved to each frame
+    camera: PerspectiveCamera,
+    /// Position of the camera
+    position: Track<Point3>,
+    /// Point where the camera is looking at
+    target: Track<Point3>,
+    /// Vertical field of view in degrees
+    vfov: Track<f64>,
+}
+
+impl AnimatedCamera {
+    /// Returns an animated camera whose tracks keep the camera's current framing, replace
+    /// them with `with_position`, `with_target` and `with_vfov`
+    ///
+    /// # Arguments
+    ///
+    /// * camera - The camera to animate, its other settings stay the same in every frame
+    pub fn new(camera: PerspectiveCamera) -> Self {
+        Self {
+            position: Track::constant(camera.origin()),
+            target: Track::constant(camera.target()),
+            vfov: Track::constant(camera.vfov()),
+            camera,
+        }
+    }
+
+    /// Returns the animated camera with the given position track
+    pub fn with_position(mut self, position: Track<Point3>) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Returns the animated camera with the given target track
+    pub fn with_target(mut self, target: Track<Point3>) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Returns the animated camera with the given vertical field of view track
+    pub fn with_vfov(mut self, vfov: Track<f64>) -> Self {
+        self.vfov = vfov;
+        self
+    }
+
+    /// Returns the camera framed at the given time, with the shutter open during the given interval
+    ///
+    /// # Arguments
+    ///
+    /// * time - Time in seconds used to evaluate the tracks
+    /// * shutter - Moments in seconds in which the shutter opens and closes
+    pub fn at(&mut self, time: f64, (time0, time1): (f64, f64)) -> &PerspectiveCamera {
+        self.camera
+            .look_at(self.position.value_at(time), self.target.value_at(time));
+        self.camera.set_vfov(self.vfov.value_at(time));
+        self.camera.set_shutter(time0, time1);
+        &self.camera
+    }
+}
+
+/// Represents the files a frame sequence is written to
+#[derive(Clone, Copy)]
+pub enum SequenceOutput {
+    /// One png file per frame, named with the prefix and the frame number (`frame_0001.png`)
+    Frames,
+    /// A single animated png file named with the prefix (`frame.png`)
+    AnimatedPng,
+}
+
+/// Represents the range of frames of an animation that will be rendered
+///
+/// Frames are numbered from 1 like their files, frame 1 starts at the time 0.
+#[derive(Clone)]
+pub struct FrameSequence {
+    /// First frame to render
+    pub first: u32,
+    /// Last frame to render, included
+    pub last: u32,
+    /// Frames per second
+    pub fps: f64,
+    /// Fraction of the frame's duration the shutter is open, 0.5 is the common 180 degrees shutter
+    pub shutter: f64,
+    /// Start of the name of the files, which end with the frame number (`frame_0001.png`)
+    pub prefix: String,
+    /// Files the frames are written to
+    pub output: SequenceOutput,
+}
+
+impl FrameSequence {
+    /// Returns a sequence of frames at the given frame rate, with a 180 degrees shutter and
+    /// files named `frame_NNNN.png`
+    ///
+    /// # Arguments
+    ///
+    /// * first - First frame to render
+    /// * last - Last frame to render, included
+    /// * fps - Frames per second
+    pub fn new(first: u32, last: u32, fps: f64) -> Self {
+        Self {
+            first,
+            last,
+            fps,
+            shutter: 0.5,
+            prefix: String::from("frame"),
+            output: SequenceOutput::Frames,
+        }
+    }
+
+    /// Returns the time in seconds in which the frame starts
+    pub fn time(&self, frame: u32) -> f64 {
+        frame.saturating_sub(1) as f64 / self.fps
+    }
+
+    /// Returns the moments in seconds in which the shutter opens and closes in the frame
+    pub fn shutter_interval(&self, frame: u32) -> (f64, f64) {
+        let time = self.time(frame);
+        (time, time + self.shutter / self.fps)
+    }
+
+    /// Returns the name of the file of the frame
+    pub fn file_name(&self, frame: u32) -> String {
+        format!("{}_{:04}.png", self.prefix, frame)
+    }
+
+    /// Returns the name of the animated png file
+    pub fn animation_file_name(&self) -> String {
+        format!("{}.png", self.prefix)
+    }
+}
+
+/// Represents a camera orbit around a target at a fixed radius and elevation, used to review
+/// assets from every side
+#[derive(Clone, Copy)]
+pub struct Turntable {
+    /// Number of frames of a full turn
+    pub frames: u32,
+    /// Distance from the camera to the target
+    pub radius: f64,
+    /// Angle above the target's horizontal plane in degrees
+    pub elevation: f64,
+    /// Frames per second
+    pub fps: f64,
+}
+
+impl Turntable {
+    /// Returns the position of the camera in the frame, the turn starts in the direction of
+    /// the +z axis and goes counterclockwise when seen from above
+    ///
+    /// # Arguments
+    ///
+    /// * target - Point the camera orbits around
+    /// * frame - Frame number, the turn repeats after `frames` frames
+    pub fn position(&self, target: Point3, frame: u32) -> Point3 {
+        let azimuth = 2.0 * PI * (frame % self.frames.max(1)) as f64 / self.frames.max(1) as f64;
+        let elevation = degress_to_radians(self.elevation);
+        target
+            + Vec3::new(
+                azimuth.sin() * elevation.cos(),
+                elevation.sin(),
+                azimuth.cos() * elevation.cos(),
+            ) * self.radius
+    }
+
+    /// Returns the camera animated along the orbit around the camera's target, with a keyframe
+    /// on each frame so the frames are exactly on the circle
+    ///
+    /// # Arguments
+    ///
+    /// * camera - The camera to move, its target is the center of the orbit
+    pub fn camera(&self, camera: PerspectiveCamera) -> AnimatedCamera {
+        let target = camera.target();
+        let mut position = Track::new(Interpolation::Linear, 0.0, self.position(target, 0));
+        for frame in 1..self.frames {
+            position = position.key(frame as f64 / self.fps, self.position(target, frame));
+        }
+        AnimatedCamera::new(camera).with_position(position)
+    }
+
+    /// Returns the sequence with the frames of a full turn, numbered from 1, with the shutter
+    /// closed so the frames are sharp
+    ///
+    /// # Arguments
+    ///
+    /// * prefix - Start of the name of the files
+    /// * output - Files the frames are written to
+    pub fn sequence(&self, prefix: &str, output: SequenceOutput) -> FrameSequence {
+        FrameSequence {
+            shutter: 0.0,
+            prefix: String::from(prefix),
+            output,
+            ..FrameSequence::new(1, self.frames.max(1), self.fps)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_tracks_interpolate_between_keyframes() {
+        let track = Track::new(Interpolation::Linear, 1.0, 10.0).key(3.0, 20.0);
+        assert_eq!(track.value_at(0.0), 10.0);
+        assert_eq!(track.value_at(1.0), 10.0);
+        assert_eq!(track.value_at(1.5), 12.5);
+        assert_eq!(track.value_at(3.0), 20.0);
+        assert_eq!(track.value_at(9.0), 20.0);
+        assert_eq!(Track::constant(4.0).value_at(2.0), 4.0);
+    }
+
+    #[test]
+    fn keyframes_are_sorted_and_replaced() {
+        let track = Track::new(Interpolation::Linear, 2.0, 2.0)
+            .key(0.0, 0.0)
+            .key(1.0, 5.0)
+            .key(1.0, 1.0);
+        assert_eq!(
+            track.keys.iter().map(|k| k.0).collect::<Vec<_>>(),
+            [0.0, 1.0, 2.0]
+        );
+        assert_eq!(track.value_at(0.5), 0.5);
+        assert_eq!(track.value_at(1.5), 1.5);
+    }
+
+    #[test]
+    fn splines_pass_through_the_keyframes_smoothly() {
+        let track = Track::new(Interpolation::Spline, 0.0, 0.0)
+            .key(1.0, 1.0)
+            .key(2.0, 0.0)
+            .key(3.0, 1.0);
+        for (time, value) in [(0.0, 0.0), (1.0, 1.0), (2.0, 0.0), (3.0, 1.0)] {
+            assert!((track.value_at(time) - value).abs() < 1e-12);
+        }
+        // The curve doesn't turn sharply at the keyframes, its slope is the same on both sides
+        let h = 1e-5;
+        let before = (track.value_at(2.0) - track.value_at(2.0 - h)) / h;
+        let after = (track.value_at(2.0 + h) - track.value_at(2.0)) / h;
+        assert!((before - after).abs() < 1e-3, "{} != {}", before, after);
+
+        // Evenly spaced keyframes on a line give the line
+        let line = Track::new(Interpolation::Spline, 0.0, 0.0)
+            .key(1.0, 2.0)
+            .key(2.0, 4.0);
+        assert!((line.value_at(0.25) - 0.5).abs() < 1e-12);
+        assert!((line.value_at(1.75) - 3.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn frames_are_numbered_from_1() {
+        let sequence = FrameSequence::new(1, 48, 24.0);
+        assert_eq!(sequence.time(1), 0.0);
+        assert_eq!(sequence.time(25), 1.0);
+        assert_eq!(sequence.shutter_interval(25), (1.0, 1.0 + 0.5 / 24.0));
+        assert_eq!(sequence.file_name(7), "frame_0007.png");
+        assert_eq!(sequence.animation_file_name(), "frame.png");
+    }
+
+    #[test]
+    fn animated_cameras_follow_their_tracks() {
+        let mut camera = AnimatedCamera::new(PerspectiveCamera::builder().build())
+            .with_position(
+                Track::new(Interpolation::Linear, 0.0, Point3::new(0.0, 0.0, 2.0))
+                    .key(2.0, Point3::new(0.0, 0.0, 4.0)),
+            )
+            .with_vfov(Track::new(Interpolation::Linear, 0.0, 30.0).key(2.0, 50.0));
+        let frame = camera.at(1.0, (1.0, 1.25));
+        assert_eq!(frame.origin(), Point3::new(0.0, 0.0, 3.0));
+        assert_eq!(frame.target(), Point3::new(0.0, 0.0, -1.0));
+        assert_eq!(frame.vfov(), 40.0);
+    }
+
+    #[test]
+    fn turntables_orbit_the_target() {
+        let turntable = Turntable {
+            frames: 4,
+            radius: 2.0,
+            elevation: 0.0,
+            fps: 4.0,
+        };
+        let target = Point3::new(1.0, 0.0, 0.0);
+        let close = |a: Point3, b: Point3| (a - b).length() < 1e-9;
+        assert!(close(
+            turntable.position(target, 0),
+            Point3::new(1.0, 0.0, 2.0)
+        ));
+        assert!(close(
+            turntable.position(target, 1),
+            Point3::new(3.0, 0.0, 0.0)
+        ));
+        assert!(close(
+            turntable.position(target, 2),
+            Point3::new(1.0, 0.0, -2.0)
+        ));
+        assert!(close(
+            turntable.position(target, 5),
+            turntable.position(target, 1)
+        ));
+
+        let above = Turntable {
+            elevation: 30.0,
+            ..turntable
+        };
+        let position = above.position(target, 0);
+        assert!(close(position, Point3::new(1.0, 1.0, 3f64.sqrt())));
+    }
+
+    #[test]
+    fn turntable_frames_are_a_sharp_full_turn() {
+        let turntable = Turntable {
+            frames: 4,
+            radius: 2.0,
+            elevation: 0.0,
+            fps: 4.0,
+        };
+        let sequence = turntable.sequence("turn", SequenceOutput::AnimatedPng);
+        assert_eq!((sequence.first, sequence.last), (1, 4));
+        assert_eq!(sequence.shutter_interval(2), (0.25, 0.25));
+        assert_eq!(sequence.animation_file_name(), "turn.png");
+
+        let camera = PerspectiveCamera::builder().lookat(Point3::zero()).build();
+        let mut camera = turntable.camera(camera);
+        for frame in sequence.first..=sequence.last {
+            let time = sequence.time(frame);
+            let origin = camera.at(time, sequence.shutter_interval(frame)).origin();
+            let expected = turntable.position(Point3::zero(), frame - 1);
+            assert!((origin - expected).length() < 1e-9, "frame {}", frame);
+        }
+    }
+}
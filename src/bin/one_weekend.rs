@@ -1,23 +1,52 @@
-use rand::prelude::*;
 use ray_tracer::{
-    camera::Camera,
+    animation::{SequenceOutput, Turntable},
+    camera::PerspectiveCamera,
     color::Color,
+    filter::{Filter, FilterKind},
     hittable::{HittableList, Sphere},
     material::{Dielectric, Lambertian, Metal},
     random_float,
-    renderer::render_image,
+    renderer::{
+        render_sequence, render_with_stats, AdaptiveSampling, Checkpoint, CropWindow,
+        RenderSettings, StopCondition,
+    },
+    seed_random,
     vec3::{Point3, Vec3},
 };
+use std::{path::Path, time::Duration};
 
+/// Usage: one_weekend [--seed N] [--checkpoint FILE] [--time SECONDS | --noise NOISE] [--progressive]
+///                    [--filter box|tent|gaussian|mitchell|blackman_harris [--filter-radius R]]
+///                    [--crop X,Y,WIDTH,HEIGHT [--composite BASE.ppm]] [--stats] [--stats-json FILE]
+///                    [--adaptive THRESHOLD] [--turntable FRAMES [--radius R] [--elevation DEGREES] [--fps FPS] [--apng]]
+///
+/// Without arguments renders a single image with 250 samples per pixel, `--time` renders for
+/// the given seconds instead and `--noise` until the average noise is under the given value or
+/// every pixel has 1000 samples.
+/// With `--progressive` `image.ppm` is rewritten after each pass of 1, 2, 4, ... samples per
+/// pixel. With `--adaptive` the noisy pixels get up to four times more samples and the others
+/// stop when their noise is under the threshold, the number of samples of each pixel is written
+/// to `samples.png`. With `--turntable` the camera orbits the scene and the frames are written
+/// as `frame_NNNN.png`, or as the animated `frame.png` with `--apng`. `--seed` changes the scene
+/// and the random numbers of the render, `--checkpoint` saves the samples to the file every
+/// minute and resumes from it if it exists. `--crop` renders only the region of the image with
+/// the given top left corner and size, into `image.ppm` or over a copy of the full image given
+/// with `--composite`. `--stats` prints the rays traced, the intersection tests and the time
+/// per tile of the render, `--stats-json` writes them to a JSON file. `--filter` splats each
+/// sample to the pixels around it with the given reconstruction filter instead of averaging the
+/// samples of each pixel, with its usual radius or the one given with `--filter-radius`.
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
     let aspect_ratio = 16.0 / 9.0;
     let image_width = 600;
     let image_height = (image_width as f64 / aspect_ratio) as i32;
     let samples_per_pixel = 250;
     let max_depth = 50;
     let image_name = String::from("image");
+    let seed = arg_value(&args, "--seed").unwrap_or(0.0) as u64;
 
     //World
+    seed_random(seed);
     let world = random_scene();
 
     //Camera
@@ -27,25 +56,199 @@ fn main() {
     let focus_dist = 10.0;
     let aperture = 0.1;
 
-    let camera = Camera::create(
-        lookfrom,
-        lookat,
-        vup,
-        20.0,
-        aspect_ratio,
-        aperture,
-        focus_dist,
-    );
+    let camera = PerspectiveCamera::builder()
+        .lookfrom(lookfrom)
+        .lookat(lookat)
+        .vup(vup)
+        .vfov(20.0)
+        .aspect_ratio(aspect_ratio)
+        .aperture(aperture)
+        .focus_dist(focus_dist)
+        .build();
+
+    if let Some(frames) = arg_value(&args, "--turntable") {
+        if !(frames >= 1.0 && frames <= u32::MAX as f64 && frames.fract() == 0.0) {
+            usage_error(&format!(
+                "--turntable needs at least 1 frame, got {}",
+                frames
+            ));
+        }
+        let fps = arg_value(&args, "--fps").unwrap_or(24.0);
+        if !(fps > 0.0 && fps.is_finite()) {
+            usage_error(&format!("--fps must be over 0, got {}", fps));
+        }
+        let turntable = Turntable {
+            frames: frames as u32,
+            radius: arg_value(&args, "--radius").unwrap_or((lookfrom - lookat).length()),
+            elevation: arg_value(&args, "--elevation").unwrap_or(10.0),
+            fps,
+        };
+        let output = if args.iter().any(|arg| arg == "--apng") {
+            SequenceOutput::AnimatedPng
+        } else {
+            SequenceOutput::Frames
+        };
+
+        let mut camera = turntable.camera(camera);
+        let settings = RenderSettings {
+            stop: StopCondition::Samples(samples_per_pixel as u32),
+            max_depth,
+            seed,
+            ..RenderSettings::new(image_width, image_height)
+        };
+        let sequence = turntable.sequence("frame", output);
+        if let Err(e) = render_sequence(&settings, &mut camera, &world, &sequence) {
+            eprintln!("one_weekend: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    render_image(
-        image_width,
-        image_height,
-        image_name,
-        samples_per_pixel,
+    let progressive = args.iter().any(|arg| arg == "--progressive");
+    let adaptive = arg_value(&args, "--adaptive").map(|noise_threshold| {
+        if !(noise_threshold > 0.0 && noise_threshold.is_finite()) {
+            usage_error(&format!(
+                "--adaptive must be over 0, got {}",
+                noise_threshold
+            ));
+        }
+        AdaptiveSampling {
+            min_samples: 16,
+            max_samples: 4 * samples_per_pixel as u32,
+            noise_threshold,
+        }
+    });
+    let stop = if let Some(seconds) = arg_value(&args, "--time") {
+        if seconds.is_nan() || seconds <= 0.0 {
+            usage_error(&format!("--time must be over 0 seconds, got {}", seconds));
+        }
+        match Duration::try_from_secs_f64(seconds) {
+            Ok(duration) => StopCondition::Time(duration),
+            Err(e) => usage_error(&format!("invalid value for --time: {}", e)),
+        }
+    } else if let Some(noise) = arg_value(&args, "--noise") {
+        if !(noise > 0.0 && noise.is_finite()) {
+            usage_error(&format!("--noise must be over 0, got {}", noise));
+        }
+        StopCondition::Noise {
+            threshold: noise,
+            max_samples: 4 * samples_per_pixel as u32,
+        }
+    } else {
+        StopCondition::Samples(adaptive.map_or(samples_per_pixel as u32, |a| a.max_samples))
+    };
+
+    let checkpoint =
+        arg_text(&args, "--checkpoint").map(|path| Checkpoint::new(path, Duration::from_secs(60)));
+
+    let crop = arg_text(&args, "--crop").map(|text| {
+        let values: Result<Vec<usize>, _> = text.split(',').map(str::parse).collect();
+        let crop = match values.as_deref() {
+            Ok(&[x, y, width, height]) => CropWindow::new(x, y, width, height),
+            _ => usage_error(&format!("invalid value for --crop: {}", text)),
+        };
+        match crop.try_clip(image_width as usize, image_height as usize) {
+            Ok(crop) => crop,
+            Err(e) => usage_error(&format!("invalid value for --crop: {}", e)),
+        }
+    });
+
+    if arg_text(&args, "--filter").is_none() && args.iter().any(|arg| arg == "--filter-radius") {
+        usage_error("--filter-radius needs --filter");
+    }
+    let filter = arg_text(&args, "--filter").map_or(Filter::default(), |name| {
+        let kind: FilterKind = match name.parse() {
+            Ok(kind) => kind,
+            Err(_) => usage_error(&format!("invalid value for --filter: {}", name)),
+        };
+        let radius = arg_value(&args, "--filter-radius").unwrap_or(kind.default_radius());
+        match Filter::new(kind, radius) {
+            Ok(filter) => filter,
+            Err(e) => usage_error(&format!("invalid value for --filter-radius: {}", e)),
+        }
+    });
+
+    let settings = RenderSettings {
+        stop,
         max_depth,
-        &camera,
-        &world,
+        progressive_output: if progressive {
+            Some(image_name.clone())
+        } else {
+            None
+        },
+        adaptive,
+        seed,
+        checkpoint,
+        crop,
+        filter,
+        ..RenderSettings::new(image_width, image_height)
+    };
+    if let Some(checkpoint) = settings.checkpoint.as_ref().filter(|c| c.path.exists()) {
+        eprintln!("Resuming from {}", checkpoint.path.display());
+    }
+    let (framebuffer, stats) = match render_with_stats(&settings, &camera, &world) {
+        Ok(rendered) => rendered,
+        Err(e) => {
+            eprintln!("one_weekend: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let (fewest, most) = framebuffer.sample_range();
+    eprintln!(
+        "{:.1} samples per pixel on average, between {} and {}",
+        framebuffer.average_samples(),
+        fewest,
+        most
+    );
+    if args.iter().any(|arg| arg == "--stats") {
+        eprintln!("{}", stats);
+    }
+    if let Some(path) = arg_text(&args, "--stats-json") {
+        if let Err(e) = stats.write_json(Path::new(path)) {
+            panic!("couldn't write {}: {}", path, e);
+        }
+    }
+    let written = match (crop, arg_text(&args, "--composite")) {
+        (Some(crop), Some(base)) => crop.composite(&framebuffer, Path::new(base), &image_name),
+        _ => framebuffer.write_ppm(&image_name),
+    };
+    if let Err(e) = written {
+        panic!("couldn't write {}.ppm: {}", image_name, e);
+    }
+    if adaptive.is_some() {
+        if let Err(e) = framebuffer.write_sample_map(Path::new("samples.png")) {
+            panic!("couldn't write samples.png: {}", e);
+        }
+    }
+}
+
+/// Prints the error with the usage and exits
+fn usage_error(message: &str) -> ! {
+    eprintln!("one_weekend: {}", message);
+    eprintln!("usage: one_weekend [--seed N] [--checkpoint FILE] [--time SECONDS | --noise NOISE] [--progressive]");
+    eprintln!(
+        "                   [--filter KIND [--filter-radius R]] [--stats] [--stats-json FILE]"
     );
+    eprintln!("                   [--crop X,Y,WIDTH,HEIGHT [--composite BASE.ppm]]");
+    eprintln!("                   [--adaptive THRESHOLD] [--turntable FRAMES [--radius R] [--elevation DEGREES] [--fps FPS] [--apng]]");
+    std::process::exit(2);
+}
+
+/// Returns the number that follows the given option in the arguments, exits with the usage if
+/// it isn't a number
+fn arg_value(args: &[String], name: &str) -> Option<f64> {
+    let i = args.iter().position(|arg| arg == name)?;
+    let value = args.get(i + 1)?;
+    match value.parse() {
+        Ok(value) => Some(value),
+        Err(_) => usage_error(&format!("invalid value for {}: {}", name, value)),
+    }
+}
+
+/// Returns the text that follows the given option in the arguments
+fn arg_text<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    let i = args.iter().position(|arg| arg == name)?;
+    args.get(i + 1).map(String::as_str)
 }
 
 fn random_scene() -> HittableList {
@@ -58,15 +261,13 @@ fn random_scene() -> HittableList {
         material_ground,
     ));
 
-    let mut rng = thread_rng();
-
     for a in -11..11 {
         for b in -11..11 {
-            let choose_mat = rng.gen::<f64>();
+            let choose_mat = random_float(0.0, 1.0);
             let center = Point3::new(
-                a as f64 + 0.9 * rng.gen::<f64>(),
+                a as f64 + 0.9 * random_float(0.0, 1.0),
                 0.2,
-                b as f64 + 0.9 * rng.gen::<f64>(),
+                b as f64 + 0.9 * random_float(0.0, 1.0),
             );
 
             if (center - Point3::new(4.0, 0.0, 0.0)).length() > 0.9 {
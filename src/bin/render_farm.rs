@@ -0,0 +1,80 @@
+use ray_tracer::{
+    distributed::{serve, Coordinator},
+    renderer::ConsoleProgress,
+    scene::Scene,
+};
+use std::{net::TcpListener, path::Path, rc::Rc, time::Duration};
+
+/// Usage: render_farm worker ADDRESS
+///        render_farm coordinator SCENE OUTPUT.png WORKER... [--tile SIZE] [--timeout SECONDS]
+///
+/// A worker listens on the address (like `0.0.0.0:7878`) and renders the tiles coordinators
+/// send it. The coordinator reads the scene file, splits the image into tiles among the
+/// workers and writes the merged image to the png file. A worker that doesn't send a tile
+/// back within `--timeout` seconds, 300 by default, fails and its tiles go to the others.
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    match args.get(1).map(String::as_str) {
+        Some("worker") if args.len() == 3 => {
+            let listener = match TcpListener::bind(&args[2]) {
+                Ok(listener) => listener,
+                Err(e) => panic!("couldn't listen on {}: {}", args[2], e),
+            };
+            eprintln!("Waiting for coordinators on {}", args[2]);
+            if let Err(e) = serve(&listener) {
+                panic!("couldn't accept connections: {}", e);
+            }
+        }
+        Some("coordinator") if args.len() >= 5 => {
+            let scene = match Scene::open(Path::new(&args[2])) {
+                Ok(scene) => scene,
+                Err(e) => panic!("couldn't read {}: {}", args[2], e),
+            };
+
+            let mut workers = Vec::new();
+            let mut tile_size = 64;
+            let mut timeout = Duration::from_secs(300);
+            let mut rest = args[4..].iter();
+            while let Some(arg) = rest.next() {
+                if arg == "--tile" {
+                    tile_size = match rest.next().map(|size| size.parse()) {
+                        Some(Ok(size)) => size,
+                        _ => panic!("invalid value for --tile"),
+                    };
+                } else if arg == "--timeout" {
+                    timeout = match rest.next().map(|seconds| seconds.parse::<f64>()) {
+                        Some(Ok(seconds)) if seconds > 0.0 && seconds.is_finite() => {
+                            Duration::from_secs_f64(seconds)
+                        }
+                        _ => panic!("invalid value for --timeout"),
+                    };
+                } else {
+                    workers.push(arg.clone());
+                }
+            }
+
+            let coordinator = Coordinator::new(workers)
+                .with_tile_size(tile_size)
+                .with_timeout(timeout)
+                .with_observer(Rc::new(ConsoleProgress));
+            let framebuffer = match coordinator.render(&scene) {
+                Ok((framebuffer, failures)) => {
+                    for failure in failures {
+                        eprintln!("worker {} failed: {}", failure.address, failure.error);
+                    }
+                    framebuffer
+                }
+                Err(e) => panic!("couldn't render {}: {}", args[2], e),
+            };
+            if let Err(e) = framebuffer.write_png(Path::new(&args[3])) {
+                panic!("couldn't write {}: {}", args[3], e);
+            }
+        }
+        _ => {
+            eprintln!("usage: render_farm worker ADDRESS");
+            eprintln!("       render_farm coordinator SCENE OUTPUT.png WORKER... [--tile SIZE] [--timeout SECONDS]");
+            std::process::exit(2);
+        }
+    }
+}
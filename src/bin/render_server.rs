@@ -0,0 +1,23 @@
+use ray_tracer::service::{serve, JobQueue};
+use std::net::TcpListener;
+
+/// Usage: render_server [ADDRESS]
+///
+/// Serves the HTTP render service on the address, `127.0.0.1:8080` by default. See the
+/// `service` module for the requests it answers.
+fn main() {
+    let address = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| String::from("127.0.0.1:8080"));
+    let listener = match TcpListener::bind(&address) {
+        Ok(listener) => listener,
+        Err(e) => panic!("couldn't listen on {}: {}", address, e),
+    };
+
+    let jobs = JobQueue::new();
+    jobs.start();
+    eprintln!("Serving on http://{}", address);
+    if let Err(e) = serve(&listener, &jobs) {
+        panic!("couldn't accept connections: {}", e);
+    }
+}
@@ -1,87 +1,109 @@
+mod aperture;
+mod builder;
+mod distortion;
+mod orthographic;
+mod panoramic;
+mod perspective;
+mod physical;
+mod stereo;
+
+pub use {
+    aperture::ApertureShape,
+    builder::PerspectiveCameraBuilder,
+    distortion::LensDistortion,
+    orthographic::OrthographicCamera,
+    panoramic::{EquirectangularCamera, FisheyeCamera, FisheyeProjection},
+    perspective::PerspectiveCamera,
+    physical::{PhysicalCamera, PhysicalSettings},
+    stereo::{Stereo, StereoCamera, StereoLayout},
+};
+
 use crate::{
-    degress_to_radians,
+    color::Color,
     ray::Ray,
     vec3::{Point3, Vec3},
 };
 
-/// Represents a camera object
-pub struct Camera {
-    /// Origin point that represents the camera's position
-    origin: Point3,
-    /// Vector that represents the horizontal length
-    horizontal: Vec3,
-    /// Vector that represents the vertical length
-    vertical: Vec3,
-    /// Position of the lower left corner
-    lower_left_corner: Vec3,
-    /// Unitary vector that represents the camera's x axis
-    u: Vec3,
-    /// Unitary vector that represents the camera's y axis
-    v: Vec3,
-    /// Half of the lens' aperture
-    lens_radius: f64,
-}
-
-impl Camera {
-    /// Creates a camera and returns it
+/// Represents a camera that casts the rays that form the image
+pub trait Camera {
+    /// Returns a ray casted from the camera through a point of the image, or None if the point
+    /// is outside of what the camera projects (like the corners of a fisheye image), in which
+    /// case that part of the image is black
     ///
     /// # Arguments
     ///
-    /// * lookfrom - position of the camera
-    /// * lookat - point where the camera is looking at
-    /// * vup - vector representing the up orientation
-    /// * vfov - vertical field of view in degress
-    /// * aspect_ratio - camera's desired aspect ratio (width/height)
-    /// * aperture - radius of the lens
-    /// * focus_dist - the distance between the sensor and the lens
-    pub fn create(
-        lookfrom: Point3,
-        lookat: Point3,
-        vup: Vec3,
-        vfov: f64,
-        aspect_ratio: f64,
-        aperture: f64,
-        focus_dist: f64,
-    ) -> Camera {
-        let theta = degress_to_radians(vfov);
-        let h = (theta / 2.0).tan();
-        let viewport_height = 2.0 * h;
-        let viewport_width = aspect_ratio * viewport_height;
+    /// * s - horizontal position in the image, from 0 (left) to 1 (right)
+    /// * t - vertical position in the image, from 0 (bottom) to 1 (top)
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray>;
 
-        let w = Vec3::unit_vector(&(lookfrom - lookat));
-        let u = Vec3::unit_vector(&Vec3::cross(&vup, &w));
-        let v = Vec3::cross(&w, &u);
-
-        let origin = lookfrom;
-        let horizontal = u * viewport_width * focus_dist;
-        let vertical = v * viewport_height * focus_dist;
-        let lower_left_corner = origin - horizontal / 2.0 - vertical / 2.0 - w * focus_dist;
-        let lens_radius = aperture / 2.0;
-
-        Camera {
-            origin,
-            horizontal,
-            vertical,
-            lower_left_corner,
-            u,
-            v,
-            lens_radius,
-        }
+    /// Returns the factor that multiplies the light that reaches the camera, 1 by default
+    fn exposure(&self) -> f64 {
+        1.0
     }
 
-    /// Returns a ray casted from the camera's lens
+    /// Returns the rays a sample through a point of the image traces, each with the color that
+    /// multiplies the light it brings, and the color of the sample is the sum of them
+    ///
+    /// By default it's the ray of `get_ray` weighted by the exposure. Cameras whose rays only
+    /// carry some of the color channels (like lenses with chromatic aberration) return a ray
+    /// for each channel.
     ///
     /// # Arguments
     ///
-    /// * s - horizontal factor, it's multiplied by horizontal vector to get the ray horizontal direction
-    /// * t - vertical factor, it's multiplied by vertical factor to get the ray vertical direction
-    pub fn get_ray(&self, s: f64, t: f64) -> Ray {
-        let rd: Vec3 = Vec3::random_in_unit_disk() * self.lens_radius;
-        let offset: Vec3 = self.u * rd.x + self.v * rd.y;
+    /// * s - horizontal position in the image, from 0 (left) to 1 (right)
+    /// * t - vertical position in the image, from 0 (bottom) to 1 (top)
+    fn get_weighted_rays(&self, s: f64, t: f64) -> Vec<(Ray, Color)> {
+        let exposure = self.exposure();
+        self.get_ray(s, t)
+            .map(|r| (r, Color::new(exposure, exposure, exposure)))
+            .into_iter()
+            .collect()
+    }
+}
+
+/// Returns the unitary vectors (u, v, w) of the camera's axes, u pointing right, v pointing up
+/// and w pointing backwards, opposite to where the camera is looking at
+///
+/// # Arguments
+///
+/// * lookfrom - position of the camera
+/// * lookat - point where the camera is looking at
+/// * vup - vector representing the up orientation
+pub(crate) fn basis(lookfrom: Point3, lookat: Point3, vup: Vec3) -> (Vec3, Vec3, Vec3) {
+    let w = Vec3::unit_vector(&(lookfrom - lookat));
+    let u = Vec3::unit_vector(&Vec3::cross(&vup, &w));
+    let v = Vec3::cross(&w, &u);
+    (u, v, w)
+}
 
-        Ray::create(
-            self.origin + offset,
-            self.lower_left_corner + self.horizontal * s + self.vertical * t - self.origin - offset,
-        )
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Checks that the vectors are the same except for rounding
+    pub(crate) fn assert_close(actual: Vec3, expected: Vec3) {
+        assert!(
+            (actual - expected).length() < 1e-9,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn the_basis_is_orthonormal_and_looks_along_minus_w() {
+        let (u, v, w) = basis(
+            Point3::new(3.0, 2.0, 1.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        for axis in [u, v, w] {
+            assert!((axis.length() - 1.0).abs() < 1e-12);
+        }
+        assert!(Vec3::dot(&u, &v).abs() < 1e-12);
+        assert!(Vec3::dot(&u, &w).abs() < 1e-12);
+        assert!(Vec3::dot(&v, &w).abs() < 1e-12);
+        assert_close(-w, Vec3::unit_vector(&Vec3::new(-3.0, -2.0, -2.0)));
+        assert!(v.y > 0.0);
     }
 }
@@ -0,0 +1,119 @@
+use crate::{random_float, texture::Texture, vec3::Vec3, PI};
+use std::rc::Rc;
+
+/// Maximum number of points tried when sampling an aperture mask before giving up
+const MASK_TRIES: usize = 64;
+
+/// Represents the shape of the lens' aperture, which is the shape of the out of focus
+/// highlights (bokeh)
+#[derive(Clone)]
+pub enum ApertureShape {
+    /// A perfect circle
+    Circular,
+    /// A regular polygon, like the opening of a diaphragm with straight blades
+    Polygon {
+        /// Number of blades, at least 3
+        blades: u32,
+        /// Rotation of the polygon in degrees
+        rotation: f64,
+    },
+    /// An arbitrary shape given by a scalar texture mapped over the square around the lens,
+    /// 0 blocks the light and 1 lets it through
+    Mask(Rc<dyn Texture>),
+}
+
+impl ApertureShape {
+    /// Returns a random point of the aperture, inside the unit disk in the xy plane
+    pub fn sample(&self) -> Vec3 {
+        match self {
+            ApertureShape::Circular => Vec3::random_in_unit_disk(),
+            ApertureShape::Polygon { blades, rotation } => {
+                let blades = (*blades).max(3) as f64;
+                let blade = (random_float(0.0, blades)).floor();
+                let angle = |i: f64| 2.0 * PI * i / blades + rotation * PI / 180.0;
+                let a = Vec3::new(angle(blade).cos(), angle(blade).sin(), 0.0);
+                let b = Vec3::new(angle(blade + 1.0).cos(), angle(blade + 1.0).sin(), 0.0);
+
+                // Uniform point in the triangle formed by the center and the blade's edge
+                let r1 = random_float(0.0, 1.0).sqrt();
+                let r2 = random_float(0.0, 1.0);
+                a * (r1 * (1.0 - r2)) + b * (r1 * r2)
+            }
+            ApertureShape::Mask(mask) => {
+                for _ in 0..MASK_TRIES {
+                    let p = Vec3::new(random_float(-1.0, 1.0), random_float(-1.0, 1.0), 0.0);
+                    let transmission = mask.value((p.x + 1.0) / 2.0, (p.y + 1.0) / 2.0, &p).x;
+                    if p.lenght_squared() < 1.0 && random_float(0.0, 1.0) < transmission {
+                        return p;
+                    }
+                }
+                Vec3::zero()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color::Color, seed_random, vec3::Point3};
+
+    /// Mask that only lets the light through the right half of the lens
+    struct RightHalf;
+
+    impl Texture for RightHalf {
+        fn value(&self, u: f64, _v: f64, _p: &Point3) -> Color {
+            let transmission = if u > 0.5 { 1.0 } else { 0.0 };
+            Color::new(transmission, transmission, transmission)
+        }
+    }
+
+    /// Returns points sampled from the aperture
+    fn samples(shape: &ApertureShape) -> Vec<Vec3> {
+        seed_random(5);
+        (0..2000).map(|_| shape.sample()).collect()
+    }
+
+    #[test]
+    fn circular_samples_are_in_the_unit_disk() {
+        for p in samples(&ApertureShape::Circular) {
+            assert!(p.lenght_squared() < 1.0 && p.z == 0.0, "{:?}", p);
+        }
+    }
+
+    #[test]
+    fn polygon_samples_are_inside_the_blades() {
+        let diamond = ApertureShape::Polygon {
+            blades: 4,
+            rotation: 0.0,
+        };
+        let points = samples(&diamond);
+        for p in &points {
+            assert!(p.x.abs() + p.y.abs() <= 1.0 + 1e-9, "{:?}", p);
+        }
+        // Every blade gets samples
+        for (x, y) in [(1.0, 1.0), (-1.0, 1.0), (-1.0, -1.0), (1.0, -1.0)] {
+            assert!(points.iter().any(|p| p.x * x > 0.0 && p.y * y > 0.0));
+        }
+
+        let square = ApertureShape::Polygon {
+            blades: 4,
+            rotation: 45.0,
+        };
+        let half_side = 0.5f64.sqrt() + 1e-9;
+        for p in samples(&square) {
+            assert!(p.x.abs() <= half_side && p.y.abs() <= half_side, "{:?}", p);
+        }
+    }
+
+    #[test]
+    fn mask_samples_are_where_the_light_goes_through() {
+        let half = ApertureShape::Mask(Rc::new(RightHalf));
+        for p in samples(&half) {
+            assert!(p.x > 0.0 && p.lenght_squared() < 1.0, "{:?}", p);
+        }
+        // A mask that blocks all the light gives the center of the lens
+        let closed = ApertureShape::Mask(Rc::new(0.0));
+        assert_eq!(closed.sample(), Vec3::zero());
+    }
+}
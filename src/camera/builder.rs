@@ -0,0 +1,182 @@
+use crate::{
+    camera::{ApertureShape, LensDistortion, PerspectiveCamera},
+    vec3::{Point3, Vec3},
+};
+
+/// Builds a `PerspectiveCamera` from named settings, the ones not given keep their defaults
+///
+/// By default the camera is at the origin looking at -z with y up, has a vertical field of
+/// view of 90 degrees, a 16:9 aspect ratio, no aperture (everything is sharp), is focused on
+/// the point it looks at, has an undistorted lens with a circular aperture and has the shutter
+/// open only at 0.
+pub struct PerspectiveCameraBuilder {
+    lookfrom: Point3,
+    lookat: Point3,
+    vup: Vec3,
+    vfov: f64,
+    aspect_ratio: f64,
+    aperture: f64,
+    /// If None the distance between lookfrom and lookat is used
+    focus_dist: Option<f64>,
+    aperture_shape: ApertureShape,
+    distortion: LensDistortion,
+    shutter: (f64, f64),
+}
+
+impl Default for PerspectiveCameraBuilder {
+    fn default() -> Self {
+        Self {
+            lookfrom: Point3::zero(),
+            lookat: Point3::new(0.0, 0.0, -1.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+            vfov: 90.0,
+            aspect_ratio: 16.0 / 9.0,
+            aperture: 0.0,
+            focus_dist: None,
+            aperture_shape: ApertureShape::Circular,
+            distortion: LensDistortion::default(),
+            shutter: (0.0, 0.0),
+        }
+    }
+}
+
+impl PerspectiveCameraBuilder {
+    /// Sets the position of the camera
+    pub fn lookfrom(mut self, lookfrom: Point3) -> Self {
+        self.lookfrom = lookfrom;
+        self
+    }
+
+    /// Sets the point where the camera is looking at
+    pub fn lookat(mut self, lookat: Point3) -> Self {
+        self.lookat = lookat;
+        self
+    }
+
+    /// Sets the vector representing the up orientation
+    pub fn vup(mut self, vup: Vec3) -> Self {
+        self.vup = vup;
+        self
+    }
+
+    /// Sets the vertical field of view in degrees
+    pub fn vfov(mut self, vfov: f64) -> Self {
+        self.vfov = vfov;
+        self
+    }
+
+    /// Sets the aspect ratio (width/height)
+    pub fn aspect_ratio(mut self, aspect_ratio: f64) -> Self {
+        self.aspect_ratio = aspect_ratio;
+        self
+    }
+
+    /// Sets the diameter of the lens' aperture
+    pub fn aperture(mut self, aperture: f64) -> Self {
+        self.aperture = aperture;
+        self
+    }
+
+    /// Sets the distance between the lens and the plane in focus
+    pub fn focus_dist(mut self, focus_dist: f64) -> Self {
+        self.focus_dist = Some(focus_dist);
+        self
+    }
+
+    /// Sets the shape of the lens' aperture
+    pub fn aperture_shape(mut self, aperture_shape: ApertureShape) -> Self {
+        self.aperture_shape = aperture_shape;
+        self
+    }
+
+    /// Sets the distortion and chromatic aberration of the lens
+    pub fn distortion(mut self, distortion: LensDistortion) -> Self {
+        self.distortion = distortion;
+        self
+    }
+
+    /// Sets the moments in seconds in which the shutter opens and closes
+    pub fn shutter(mut self, time0: f64, time1: f64) -> Self {
+        self.shutter = (time0, time1);
+        self
+    }
+
+    /// Returns the camera with the given settings
+    pub fn build(self) -> PerspectiveCamera {
+        let focus_dist = self
+            .focus_dist
+            .unwrap_or_else(|| (self.lookfrom - self.lookat).length());
+
+        let mut camera = PerspectiveCamera::create(
+            self.lookfrom,
+            self.lookat,
+            self.vup,
+            self.vfov,
+            self.aspect_ratio,
+            self.aperture,
+            focus_dist,
+        );
+        camera.set_aperture_shape(self.aperture_shape);
+        camera.set_distortion(self.distortion);
+        camera.set_shutter(self.shutter.0, self.shutter.1);
+        camera
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::{tests::assert_close, Camera};
+
+    #[test]
+    fn unset_settings_keep_their_defaults() {
+        let camera = PerspectiveCamera::builder().build();
+        assert_eq!(camera.origin(), Point3::zero());
+        assert_eq!(camera.target(), Point3::new(0.0, 0.0, -1.0));
+        assert_eq!(camera.vfov(), 90.0);
+        assert_eq!(camera.aspect_ratio(), 16.0 / 9.0);
+        assert_eq!(camera.aperture(), 0.0);
+        assert_eq!(camera.focus_dist(), 1.0);
+        let ray = camera.get_ray(0.5, 0.5).unwrap();
+        assert_eq!(ray.time, 0.0);
+        assert_close(ray.dir, Vec3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn the_camera_has_the_given_settings() {
+        let camera = PerspectiveCamera::builder()
+            .lookfrom(Point3::new(0.0, 0.0, 4.0))
+            .lookat(Point3::zero())
+            .vfov(40.0)
+            .aspect_ratio(1.5)
+            .aperture(0.1)
+            .build();
+        // The camera is focused on the point it looks at unless told otherwise
+        assert_eq!(camera.focus_dist(), 4.0);
+        assert_eq!(camera.vfov(), 40.0);
+        assert_eq!(camera.aspect_ratio(), 1.5);
+        assert_eq!(camera.aperture(), 0.1);
+
+        let camera = PerspectiveCamera::builder()
+            .lookfrom(Point3::new(0.0, 0.0, 4.0))
+            .lookat(Point3::zero())
+            .focus_dist(2.5)
+            .shutter(1.0, 1.5)
+            .build();
+        assert_eq!(camera.focus_dist(), 2.5);
+        let time = camera.get_ray(0.5, 0.5).unwrap().time;
+        assert!((1.0..=1.5).contains(&time), "{}", time);
+    }
+
+    #[test]
+    fn cameras_can_be_moved_after_they_are_built() {
+        let mut camera = PerspectiveCamera::builder().build();
+        camera.look_at(Point3::new(5.0, 0.0, 0.0), Point3::zero());
+        assert_close(camera.w(), Vec3::new(1.0, 0.0, 0.0));
+        assert_close(camera.u(), Vec3::new(0.0, 0.0, -1.0));
+        camera.focus_on(Point3::new(-3.0, 2.0, 0.0));
+        assert_eq!(camera.focus_dist(), 8.0);
+        let ray = camera.get_ray(0.5, 0.5).unwrap();
+        assert_close(ray.origin + ray.dir, Point3::new(-3.0, 0.0, 0.0));
+    }
+}
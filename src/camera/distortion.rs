@@ -0,0 +1,171 @@
+/// Represents the Brown-Conrady model of the distortion of a real lens, with the same
+/// coefficients used by calibration tools like OpenCV, plus lateral chromatic aberration
+///
+/// The coefficients act on image coordinates normalized so the image is 2 units tall,
+/// with the origin at its center.
+#[derive(Clone, Copy, Default)]
+pub struct LensDistortion {
+    /// First radial coefficient, negative values give barrel and positive values pincushion distortion
+    pub k1: f64,
+    /// Second radial coefficient
+    pub k2: f64,
+    /// Third radial coefficient
+    pub k3: f64,
+    /// First tangential coefficient
+    pub p1: f64,
+    /// Second tangential coefficient
+    pub p2: f64,
+    /// Lateral chromatic aberration, the red image is scaled by 1 + c and the blue one by 1 - c
+    pub chromatic_aberration: f64,
+}
+
+/// Most iterations used to invert the distortion, strong distortions near the corners of wide
+/// images need a few dozens
+const MAX_UNDISTORT_ITERATIONS: usize = 100;
+/// Distance in normalized coordinates under which the inverted distortion is exact enough
+const UNDISTORT_TOLERANCE: f64 = 1e-9;
+
+impl LensDistortion {
+    /// Returns true if the lens doesn't distort the image
+    pub fn is_none(&self) -> bool {
+        self.k1 == 0.0
+            && self.k2 == 0.0
+            && self.k3 == 0.0
+            && self.p1 == 0.0
+            && self.p2 == 0.0
+            && self.chromatic_aberration == 0.0
+    }
+
+    /// Returns where a point of the ideal image ends up in the distorted image
+    ///
+    /// # Arguments
+    ///
+    /// * (x, y) - normalized coordinates of the point in the ideal image
+    pub fn distort(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        let r2 = x * x + y * y;
+        let radial = 1.0 + r2 * (self.k1 + r2 * (self.k2 + r2 * self.k3));
+        (
+            x * radial + 2.0 * self.p1 * x * y + self.p2 * (r2 + 2.0 * x * x),
+            y * radial + self.p1 * (r2 + 2.0 * y * y) + 2.0 * self.p2 * x * y,
+        )
+    }
+
+    /// Returns the point of the ideal image that ends up at the given point of the distorted
+    /// image, which is where the camera's ray must go through
+    ///
+    /// # Arguments
+    ///
+    /// * (x, y) - normalized coordinates of the point in the distorted image
+    /// * channel_scale - scale of the image of the color channel, for the chromatic aberration
+    pub fn undistort(&self, (x, y): (f64, f64), channel_scale: f64) -> (f64, f64) {
+        let (xd, yd) = (x / channel_scale, y / channel_scale);
+        let (mut xu, mut yu) = (xd, yd);
+        for _ in 0..MAX_UNDISTORT_ITERATIONS {
+            let (xe, ye) = self.distort((xu, yu));
+            xu += xd - xe;
+            yu += yd - ye;
+            if (xd - xe).abs() < UNDISTORT_TOLERANCE && (yd - ye).abs() < UNDISTORT_TOLERANCE {
+                break;
+            }
+        }
+        (xu, yu)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        camera::{Camera, PerspectiveCamera},
+        color::Color,
+    };
+
+    /// Returns a lens with mild barrel and tangential distortion
+    fn lens() -> LensDistortion {
+        LensDistortion {
+            k1: -0.08,
+            k2: 0.01,
+            k3: 0.001,
+            p1: 0.001,
+            p2: -0.002,
+            ..LensDistortion::default()
+        }
+    }
+
+    #[test]
+    fn undistort_is_the_inverse_of_distort() {
+        let lens = lens();
+        assert!(!lens.is_none());
+        for point in [
+            (0.0, 0.0),
+            (0.3, -0.2),
+            (-0.9, 0.6),
+            (1.5, 1.0),
+            (-1.7, -1.0),
+        ] {
+            let (x, y) = lens.undistort(lens.distort(point), 1.0);
+            assert!(
+                (x - point.0).abs() < 1e-6 && (y - point.1).abs() < 1e-6,
+                "{:?} != {:?}",
+                (x, y),
+                point
+            );
+        }
+    }
+
+    #[test]
+    fn strong_distortion_is_inverted_in_the_corners() {
+        // The corners of a 3:2 image are where the inversion converges the slowest
+        let barrel = LensDistortion {
+            k1: -0.08,
+            ..LensDistortion::default()
+        };
+        let (x, y) = barrel.undistort(barrel.distort((1.5, 1.0)), 1.0);
+        assert!(
+            (x - 1.5).abs() < 1e-6 && (y - 1.0).abs() < 1e-6,
+            "{:?}",
+            (x, y)
+        );
+    }
+
+    #[test]
+    fn barrel_distortion_pulls_the_edges_in() {
+        let barrel = LensDistortion {
+            k1: -0.1,
+            ..LensDistortion::default()
+        };
+        let (x, y) = barrel.distort((1.0, 0.0));
+        assert!((x - 0.9).abs() < 1e-12 && y == 0.0);
+        assert_eq!(barrel.distort((0.0, 0.0)), (0.0, 0.0));
+        assert!(LensDistortion::default().is_none());
+    }
+
+    #[test]
+    fn channels_are_scaled_by_the_chromatic_aberration() {
+        let aberration = LensDistortion {
+            chromatic_aberration: 0.01,
+            ..LensDistortion::default()
+        };
+        assert!(!aberration.is_none());
+        assert_eq!(aberration.undistort((1.0, 0.5), 2.0), (0.5, 0.25));
+
+        // Each channel traces its own ray, and the red image is the biggest so its rays are
+        // closer to the center
+        let camera = PerspectiveCamera::builder()
+            .aspect_ratio(1.0)
+            .distortion(aberration)
+            .build();
+        let rays = camera.get_weighted_rays(1.0, 0.5);
+        let weights: Vec<Color> = rays.iter().map(|(_, weight)| *weight).collect();
+        assert_eq!(
+            weights,
+            [
+                Color::new(1.0, 0.0, 0.0),
+                Color::new(0.0, 1.0, 0.0),
+                Color::new(0.0, 0.0, 1.0)
+            ]
+        );
+        let x: Vec<f64> = rays.iter().map(|(ray, _)| ray.dir.x).collect();
+        assert!(x[0] < x[1] && x[1] < x[2], "{:?}", x);
+    }
+}
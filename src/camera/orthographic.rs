@@ -0,0 +1,86 @@
+use crate::{
+    camera::{basis, Camera},
+    ray::Ray,
+    vec3::{Point3, Vec3},
+};
+
+/// Represents an orthographic camera, where all rays are parallel so objects keep their size
+/// no matter how far they are, used for technical and architectural renders
+pub struct OrthographicCamera {
+    /// Position of the lower left corner of the view
+    lower_left_corner: Point3,
+    /// Vector that represents the horizontal length of the view
+    horizontal: Vec3,
+    /// Vector that represents the vertical length of the view
+    vertical: Vec3,
+    /// Direction of all the rays
+    direction: Vec3,
+}
+
+impl OrthographicCamera {
+    /// Creates an orthographic camera and returns it
+    ///
+    /// # Arguments
+    ///
+    /// * lookfrom - position of the center of the view
+    /// * lookat - point where the camera is looking at
+    /// * vup - vector representing the up orientation
+    /// * view_width - width of the area seen by the camera, in world units
+    /// * view_height - height of the area seen by the camera, in world units
+    pub fn create(
+        lookfrom: Point3,
+        lookat: Point3,
+        vup: Vec3,
+        view_width: f64,
+        view_height: f64,
+    ) -> OrthographicCamera {
+        let (u, v, w) = basis(lookfrom, lookat, vup);
+
+        let horizontal = u * view_width;
+        let vertical = v * view_height;
+        let lower_left_corner = lookfrom - horizontal / 2.0 - vertical / 2.0;
+
+        OrthographicCamera {
+            lower_left_corner,
+            horizontal,
+            vertical,
+            direction: -w,
+        }
+    }
+}
+
+impl Camera for OrthographicCamera {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        Some(Ray::create(
+            self.lower_left_corner + self.horizontal * s + self.vertical * t,
+            self.direction,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::tests::assert_close;
+
+    #[test]
+    fn rays_are_parallel_and_cover_the_view() {
+        let camera = OrthographicCamera::create(
+            Point3::new(0.0, 0.0, 5.0),
+            Point3::zero(),
+            Vec3::new(0.0, 1.0, 0.0),
+            4.0,
+            2.0,
+        );
+        let center = camera.get_ray(0.5, 0.5).unwrap();
+        assert_close(center.origin, Point3::new(0.0, 0.0, 5.0));
+        assert_close(center.dir, Vec3::new(0.0, 0.0, -1.0));
+
+        let lower_left = camera.get_ray(0.0, 0.0).unwrap();
+        assert_close(lower_left.origin, Point3::new(-2.0, -1.0, 5.0));
+        assert_close(lower_left.dir, center.dir);
+        let upper_right = camera.get_ray(1.0, 1.0).unwrap();
+        assert_close(upper_right.origin, Point3::new(2.0, 1.0, 5.0));
+        assert_close(upper_right.dir, center.dir);
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{
+    camera::{basis, Camera},
+    degress_to_radians,
+    ray::Ray,
+    vec3::{Point3, Vec3},
+    PI,
+};
+
+/// Represents a 360 degrees camera that uses the equirectangular (latitude-longitude)
+/// projection, the image covers the whole sphere around the camera and should have an aspect
+/// ratio of 2:1
+pub struct EquirectangularCamera {
+    /// Origin point that represents the camera's position
+    origin: Point3,
+    /// Unitary vector that represents the camera's x axis
+    u: Vec3,
+    /// Unitary vector that represents the camera's y axis
+    v: Vec3,
+    /// Unitary vector that points to the center of the image
+    forward: Vec3,
+}
+
+impl EquirectangularCamera {
+    /// Creates an equirectangular camera and returns it
+    ///
+    /// # Arguments
+    ///
+    /// * lookfrom - position of the camera
+    /// * lookat - point that will be at the center of the image
+    /// * vup - vector representing the up orientation
+    pub fn create(lookfrom: Point3, lookat: Point3, vup: Vec3) -> EquirectangularCamera {
+        let (u, v, w) = basis(lookfrom, lookat, vup);
+        EquirectangularCamera {
+            origin: lookfrom,
+            u,
+            v,
+            forward: -w,
+        }
+    }
+}
+
+impl Camera for EquirectangularCamera {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        let longitude = (s - 0.5) * 2.0 * PI;
+        let latitude = (t - 0.5) * PI;
+
+        let horizontal = self.u * longitude.sin() + self.forward * longitude.cos();
+        let direction = horizontal * latitude.cos() + self.v * latitude.sin();
+
+        Some(Ray::create(self.origin, direction))
+    }
+}
+
+/// Represents how a fisheye lens maps the angle of the incoming light to the distance from
+/// the center of the image
+#[derive(Clone, Copy)]
+pub enum FisheyeProjection {
+    /// The distance is proportional to the angle, used for dome projections
+    Equidistant,
+    /// Every pixel covers the same solid angle, common in real fisheye lenses
+    Equisolid,
+}
+
+/// Represents a fisheye camera, the image is a circle centered in the image and as tall as it,
+/// what is outside of the circle is black
+pub struct FisheyeCamera {
+    /// Origin point that represents the camera's position
+    origin: Point3,
+    /// Unitary vector that represents the camera's x axis
+    u: Vec3,
+    /// Unitary vector that represents the camera's y axis
+    v: Vec3,
+    /// Unitary vector that points to the center of the image
+    forward: Vec3,
+    /// Half of the field of view in radians
+    half_fov: f64,
+    /// Image's aspect ratio (width/height)
+    aspect_ratio: f64,
+    /// Mapping between angles and distances in the image
+    projection: FisheyeProjection,
+}
+
+impl FisheyeCamera {
+    /// Creates a fisheye camera and returns it
+    ///
+    /// # Arguments
+    ///
+    /// * lookfrom - position of the camera
+    /// * lookat - point that will be at the center of the image
+    /// * vup - vector representing the up orientation
+    /// * fov - field of view across the circle in degrees, 360 covers the whole sphere
+    /// * aspect_ratio - image's aspect ratio (width/height)
+    /// * projection - mapping between angles and distances in the image
+    pub fn create(
+        lookfrom: Point3,
+        lookat: Point3,
+        vup: Vec3,
+        fov: f64,
+        aspect_ratio: f64,
+        projection: FisheyeProjection,
+    ) -> FisheyeCamera {
+        let (u, v, w) = basis(lookfrom, lookat, vup);
+        FisheyeCamera {
+            origin: lookfrom,
+            u,
+            v,
+            forward: -w,
+            half_fov: degress_to_radians(fov.min(360.0)) / 2.0,
+            aspect_ratio,
+            projection,
+        }
+    }
+}
+
+impl Camera for FisheyeCamera {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        let x = (2.0 * s - 1.0) * self.aspect_ratio;
+        let y = 2.0 * t - 1.0;
+        let r = (x * x + y * y).sqrt();
+        if r > 1.0 {
+            return None;
+        }
+
+        let theta = match self.projection {
+            FisheyeProjection::Equidistant => r * self.half_fov,
+            FisheyeProjection::Equisolid => 2.0 * (r * (self.half_fov / 2.0).sin()).asin(),
+        };
+
+        let radial = if r > 0.0 {
+            (self.u * x + self.v * y) / r
+        } else {
+            Vec3::zero()
+        };
+        let direction = radial * theta.sin() + self.forward * theta.cos();
+
+        Some(Ray::create(self.origin, direction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::tests::assert_close;
+
+    /// Returns the unit direction of the ray the camera casts through the point of the image
+    fn direction(camera: &impl Camera, s: f64, t: f64) -> Vec3 {
+        Vec3::unit_vector(&camera.get_ray(s, t).unwrap().dir)
+    }
+
+    /// Returns a fisheye camera at the origin looking along -z with a square image
+    fn fisheye(fov: f64, projection: FisheyeProjection) -> FisheyeCamera {
+        FisheyeCamera::create(
+            Point3::zero(),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            fov,
+            1.0,
+            projection,
+        )
+    }
+
+    #[test]
+    fn equirectangular_images_cover_the_whole_sphere() {
+        let camera = EquirectangularCamera::create(
+            Point3::zero(),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        assert_close(direction(&camera, 0.5, 0.5), Vec3::new(0.0, 0.0, -1.0));
+        assert_close(direction(&camera, 0.75, 0.5), Vec3::new(1.0, 0.0, 0.0));
+        assert_close(direction(&camera, 0.25, 0.5), Vec3::new(-1.0, 0.0, 0.0));
+        assert_close(direction(&camera, 0.0, 0.5), Vec3::new(0.0, 0.0, 1.0));
+        assert_close(direction(&camera, 0.5, 1.0), Vec3::new(0.0, 1.0, 0.0));
+        assert_close(direction(&camera, 0.5, 0.0), Vec3::new(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn fisheye_images_are_a_circle() {
+        let camera = fisheye(180.0, FisheyeProjection::Equidistant);
+        assert_close(direction(&camera, 0.5, 0.5), Vec3::new(0.0, 0.0, -1.0));
+        assert_close(direction(&camera, 1.0, 0.5), Vec3::new(1.0, 0.0, 0.0));
+        assert_close(direction(&camera, 0.5, 1.0), Vec3::new(0.0, 1.0, 0.0));
+        assert!(camera.get_ray(1.0, 1.0).is_none());
+        assert!(camera.get_ray(0.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn fisheye_projections_map_the_distance_to_the_angle() {
+        // Halfway to the edge of a 180 degrees lens
+        let angle = |projection| {
+            let forward = Vec3::new(0.0, 0.0, -1.0);
+            let d = direction(&fisheye(180.0, projection), 0.75, 0.5);
+            Vec3::dot(&d, &forward).acos()
+        };
+        assert!((angle(FisheyeProjection::Equidistant) - PI / 4.0).abs() < 1e-9);
+        let equisolid = 2.0 * (0.5 * (PI / 4.0).sin()).asin();
+        assert!((angle(FisheyeProjection::Equisolid) - equisolid).abs() < 1e-9);
+    }
+}
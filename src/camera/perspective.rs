@@ -0,0 +1,322 @@
+use crate::{
+    camera::{basis, ApertureShape, Camera, LensDistortion, PerspectiveCameraBuilder},
+    color::Color,
+    degress_to_radians, random_float,
+    ray::Ray,
+    vec3::{Point3, Vec3},
+};
+
+/// Represents a perspective camera with a thin lens that can blur what is out of focus
+///
+/// The camera keeps the parameters it was created with, so it can be moved, retargeted,
+/// zoomed or refocused afterwards, for interactive or animated use.
+pub struct PerspectiveCamera {
+    /// Position of the camera
+    lookfrom: Point3,
+    /// Point where the camera is looking at
+    lookat: Point3,
+    /// Vector representing the up orientation
+    vup: Vec3,
+    /// Vertical field of view in degress
+    vfov: f64,
+    /// Aspect ratio (width/height)
+    aspect_ratio: f64,
+    /// Diameter of the lens' aperture
+    aperture: f64,
+    /// Distance between the lens and the plane in focus
+    focus_dist: f64,
+    /// Displacement of the view window, see `shift_view`
+    view_shift: Vec3,
+    /// Origin point that represents the camera's position
+    origin: Point3,
+    /// Vector that represents the horizontal length
+    horizontal: Vec3,
+    /// Vector that represents the vertical length
+    vertical: Vec3,
+    /// Position of the lower left corner
+    lower_left_corner: Vec3,
+    /// Unitary vector that represents the camera's x axis
+    u: Vec3,
+    /// Unitary vector that represents the camera's y axis
+    v: Vec3,
+    /// Unitary vector that represents the camera's z axis, pointing backwards
+    w: Vec3,
+    /// Half of the lens' aperture
+    lens_radius: f64,
+    /// Shape of the lens' aperture
+    aperture_shape: ApertureShape,
+    /// Distortion and chromatic aberration of the lens
+    distortion: LensDistortion,
+    /// Moment in seconds in which the shutter opens
+    time0: f64,
+    /// Moment in seconds in which the shutter closes
+    time1: f64,
+}
+
+impl PerspectiveCamera {
+    /// Creates a camera and returns it
+    ///
+    /// # Arguments
+    ///
+    /// * lookfrom - position of the camera
+    /// * lookat - point where the camera is looking at
+    /// * vup - vector representing the up orientation
+    /// * vfov - vertical field of view in degress
+    /// * aspect_ratio - camera's desired aspect ratio (width/height)
+    /// * aperture - diameter of the lens
+    /// * focus_dist - the distance between the lens and the plane in focus
+    pub fn create(
+        lookfrom: Point3,
+        lookat: Point3,
+        vup: Vec3,
+        vfov: f64,
+        aspect_ratio: f64,
+        aperture: f64,
+        focus_dist: f64,
+    ) -> PerspectiveCamera {
+        let mut camera = PerspectiveCamera {
+            lookfrom,
+            lookat,
+            vup,
+            vfov,
+            aspect_ratio,
+            aperture,
+            focus_dist,
+            view_shift: Vec3::zero(),
+            origin: lookfrom,
+            horizontal: Vec3::zero(),
+            vertical: Vec3::zero(),
+            lower_left_corner: Vec3::zero(),
+            u: Vec3::zero(),
+            v: Vec3::zero(),
+            w: Vec3::zero(),
+            lens_radius: 0.0,
+            aperture_shape: ApertureShape::Circular,
+            distortion: LensDistortion::default(),
+            time0: 0.0,
+            time1: 0.0,
+        };
+        camera.update();
+        camera
+    }
+
+    /// Returns a builder to create a camera with named settings and default values
+    pub fn builder() -> PerspectiveCameraBuilder {
+        PerspectiveCameraBuilder::default()
+    }
+
+    /// Recomputes the camera's basis and view window from its parameters
+    fn update(&mut self) {
+        let theta = degress_to_radians(self.vfov);
+        let h = (theta / 2.0).tan();
+        let viewport_height = 2.0 * h;
+        let viewport_width = self.aspect_ratio * viewport_height;
+
+        let (u, v, w) = basis(self.lookfrom, self.lookat, self.vup);
+
+        self.origin = self.lookfrom;
+        self.horizontal = u * viewport_width * self.focus_dist;
+        self.vertical = v * viewport_height * self.focus_dist;
+        self.lower_left_corner = self.origin - self.horizontal / 2.0 - self.vertical / 2.0
+            + self.view_shift
+            - w * self.focus_dist;
+        self.lens_radius = self.aperture / 2.0;
+        self.u = u;
+        self.v = v;
+        self.w = w;
+    }
+
+    /// Returns the position of the camera
+    pub fn origin(&self) -> Point3 {
+        self.origin
+    }
+
+    /// Returns the point where the camera is looking at
+    pub fn target(&self) -> Point3 {
+        self.lookat
+    }
+
+    /// Returns the unitary vector that points to the right of the image
+    pub fn u(&self) -> Vec3 {
+        self.u
+    }
+
+    /// Returns the unitary vector that points to the top of the image
+    pub fn v(&self) -> Vec3 {
+        self.v
+    }
+
+    /// Returns the unitary vector that points backwards, opposite to where the camera looks
+    pub fn w(&self) -> Vec3 {
+        self.w
+    }
+
+    /// Returns the vertical field of view in degrees
+    pub fn vfov(&self) -> f64 {
+        self.vfov
+    }
+
+    /// Returns the aspect ratio (width/height)
+    pub fn aspect_ratio(&self) -> f64 {
+        self.aspect_ratio
+    }
+
+    /// Returns the diameter of the lens' aperture
+    pub fn aperture(&self) -> f64 {
+        self.aperture
+    }
+
+    /// Returns the distance between the lens and the plane in focus
+    pub fn focus_dist(&self) -> f64 {
+        self.focus_dist
+    }
+
+    /// Moves the camera keeping the point it's looking at
+    pub fn set_position(&mut self, lookfrom: Point3) {
+        self.lookfrom = lookfrom;
+        self.update();
+    }
+
+    /// Turns the camera to look at another point
+    pub fn set_target(&mut self, lookat: Point3) {
+        self.lookat = lookat;
+        self.update();
+    }
+
+    /// Moves the camera and turns it to look at the given point
+    pub fn look_at(&mut self, lookfrom: Point3, lookat: Point3) {
+        self.lookfrom = lookfrom;
+        self.lookat = lookat;
+        self.update();
+    }
+
+    /// Changes the up orientation of the camera
+    pub fn set_vup(&mut self, vup: Vec3) {
+        self.vup = vup;
+        self.update();
+    }
+
+    /// Changes the vertical field of view, in degrees
+    pub fn set_vfov(&mut self, vfov: f64) {
+        self.vfov = vfov;
+        self.update();
+    }
+
+    /// Changes the aspect ratio (width/height)
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: f64) {
+        self.aspect_ratio = aspect_ratio;
+        self.update();
+    }
+
+    /// Changes the diameter of the lens' aperture, 0 makes everything sharp
+    pub fn set_aperture(&mut self, aperture: f64) {
+        self.aperture = aperture;
+        self.update();
+    }
+
+    /// Changes the distance between the lens and the plane in focus
+    pub fn set_focus_dist(&mut self, focus_dist: f64) {
+        self.focus_dist = focus_dist;
+        self.update();
+    }
+
+    /// Puts the given point in focus
+    pub fn focus_on(&mut self, point: Point3) {
+        self.set_focus_dist(Vec3::dot(&(self.lookfrom - point), &self.w).abs());
+    }
+
+    /// Changes the interval in which the shutter is open, rays are cast at random moments in
+    /// it so moving objects blur, by default the shutter opens and closes at 0
+    ///
+    /// # Arguments
+    ///
+    /// * time0 - moment in seconds in which the shutter opens
+    /// * time1 - moment in seconds in which the shutter closes
+    pub fn set_shutter(&mut self, time0: f64, time1: f64) {
+        self.time0 = time0;
+        self.time1 = time1;
+    }
+
+    /// Changes the shape of the lens' aperture, which is circular by default
+    ///
+    /// # Arguments
+    ///
+    /// * shape - the new shape of the aperture
+    pub fn set_aperture_shape(&mut self, shape: ApertureShape) {
+        self.aperture_shape = shape;
+    }
+
+    /// Changes the distortion and chromatic aberration of the lens, which has none by default
+    ///
+    /// # Arguments
+    ///
+    /// * distortion - the new distortion of the lens
+    pub fn set_distortion(&mut self, distortion: LensDistortion) {
+        self.distortion = distortion;
+    }
+
+    /// Returns the ray through a point of the distorted image
+    ///
+    /// # Arguments
+    ///
+    /// * s - horizontal position in the image, from 0 (left) to 1 (right)
+    /// * t - vertical position in the image, from 0 (bottom) to 1 (top)
+    /// * channel_scale - scale of the image of the traced color channel
+    fn distorted_ray(&self, s: f64, t: f64, channel_scale: f64) -> Ray {
+        let (s, t) = if self.distortion.is_none() {
+            (s, t)
+        } else {
+            let point = ((2.0 * s - 1.0) * self.aspect_ratio, 2.0 * t - 1.0);
+            let (x, y) = self.distortion.undistort(point, channel_scale);
+            ((x / self.aspect_ratio + 1.0) / 2.0, (y + 1.0) / 2.0)
+        };
+
+        let rd: Vec3 = self.aperture_shape.sample() * self.lens_radius;
+        let offset: Vec3 = self.u * rd.x + self.v * rd.y;
+
+        Ray::with_time(
+            self.origin + offset,
+            self.lower_left_corner + self.horizontal * s + self.vertical * t - self.origin - offset,
+            random_float(self.time0, self.time1),
+        )
+    }
+
+    /// Moves the view window (the plane in focus) without moving the camera, giving an
+    /// off-axis frustum like a shift lens
+    ///
+    /// # Arguments
+    ///
+    /// * offset - displacement of the view window
+    pub(crate) fn shift_view(&mut self, offset: Vec3) {
+        self.view_shift = offset;
+        self.update();
+    }
+}
+
+impl Camera for PerspectiveCamera {
+    /// Returns the ray through a point of the image, with chromatic aberration it's the ray of
+    /// the green channel, whose image isn't scaled, see `get_weighted_rays` for all of them
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        Some(self.distorted_ray(s, t, 1.0))
+    }
+
+    fn get_weighted_rays(&self, s: f64, t: f64) -> Vec<(Ray, Color)> {
+        let aberration = self.distortion.chromatic_aberration;
+        if aberration == 0.0 {
+            return vec![(self.distorted_ray(s, t, 1.0), Color::new(1.0, 1.0, 1.0))];
+        }
+
+        // The image of each channel is scaled differently, so each one traces its own ray
+        vec![
+            (
+                self.distorted_ray(s, t, 1.0 + aberration),
+                Color::new(1.0, 0.0, 0.0),
+            ),
+            (self.distorted_ray(s, t, 1.0), Color::new(0.0, 1.0, 0.0)),
+            (
+                self.distorted_ray(s, t, 1.0 - aberration),
+                Color::new(0.0, 0.0, 1.0),
+            ),
+        ]
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{
+    camera::{Camera, PerspectiveCamera},
+    color::Color,
+    ray::Ray,
+    vec3::{Point3, Vec3},
+};
+
+/// Represents the settings of a real camera, lengths of the lens and sensor are in millimeters
+/// and the scene is assumed to be in meters
+#[derive(Clone, Copy)]
+pub struct PhysicalSettings {
+    /// Focal length of the lens in millimeters
+    pub focal_length: f64,
+    /// Width of the sensor in millimeters
+    pub sensor_width: f64,
+    /// Height of the sensor in millimeters
+    pub sensor_height: f64,
+    /// Ratio between the focal length and the diameter of the aperture (the N in f/N)
+    pub f_stop: f64,
+    /// Time in seconds the shutter stays open
+    pub shutter_speed: f64,
+    /// Moment in seconds in which the shutter opens
+    pub shutter_open: f64,
+    /// Sensitivity of the sensor
+    pub iso: f64,
+}
+
+impl Default for PhysicalSettings {
+    /// A 50mm lens on a full frame sensor, at f/8, 1/100s and ISO 100
+    fn default() -> Self {
+        Self {
+            focal_length: 50.0,
+            sensor_width: 36.0,
+            sensor_height: 24.0,
+            f_stop: 8.0,
+            shutter_speed: 1.0 / 100.0,
+            shutter_open: 0.0,
+            iso: 100.0,
+        }
+    }
+}
+
+impl PhysicalSettings {
+    /// Returns the vertical field of view in degrees given by the focal length and the sensor
+    pub fn vfov(&self) -> f64 {
+        (2.0 * (self.sensor_height / (2.0 * self.focal_length)).atan()).to_degrees()
+    }
+
+    /// Returns the diameter of the aperture in meters
+    pub fn aperture(&self) -> f64 {
+        self.focal_length / 1000.0 / self.f_stop
+    }
+
+    /// Returns the factor that multiplies the light, calibrated so that f/8, 1/100s and ISO 100
+    /// gives 1, each stop of aperture, shutter or ISO doubles or halves it
+    pub fn exposure(&self) -> f64 {
+        (self.shutter_speed * 100.0) * (self.iso / 100.0) * (8.0 / self.f_stop).powi(2)
+    }
+}
+
+/// Represents a camera defined by physical settings, from which the field of view, the depth
+/// of field, the motion blur and the exposure are derived
+pub struct PhysicalCamera {
+    /// Thin lens camera that casts the rays
+    camera: PerspectiveCamera,
+    /// Factor that multiplies the light
+    exposure: f64,
+}
+
+impl PhysicalCamera {
+    /// Creates a physical camera, the image should have the aspect ratio of the sensor
+    ///
+    /// # Arguments
+    ///
+    /// * lookfrom - position of the camera
+    /// * lookat - point where the camera is looking at
+    /// * vup - vector representing the up orientation
+    /// * focus_dist - the distance in meters to the plane in focus
+    /// * settings - the settings of the lens, sensor and shutter
+    pub fn create(
+        lookfrom: Point3,
+        lookat: Point3,
+        vup: Vec3,
+        focus_dist: f64,
+        settings: PhysicalSettings,
+    ) -> PhysicalCamera {
+        let mut camera = PerspectiveCamera::create(
+            lookfrom,
+            lookat,
+            vup,
+            settings.vfov(),
+            settings.sensor_width / settings.sensor_height,
+            settings.aperture(),
+            focus_dist,
+        );
+        camera.set_shutter(
+            settings.shutter_open,
+            settings.shutter_open + settings.shutter_speed,
+        );
+
+        PhysicalCamera {
+            camera,
+            exposure: settings.exposure(),
+        }
+    }
+}
+
+impl Camera for PhysicalCamera {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        self.camera.get_ray(s, t)
+    }
+
+    fn exposure(&self) -> f64 {
+        self.exposure
+    }
+
+    fn get_weighted_rays(&self, s: f64, t: f64) -> Vec<(Ray, Color)> {
+        self.camera
+            .get_weighted_rays(s, t)
+            .into_iter()
+            .map(|(r, weight)| (r, weight * self.exposure))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seed_random;
+
+    #[test]
+    fn the_settings_give_the_lens_and_the_exposure() {
+        let settings = PhysicalSettings::default();
+        assert!((settings.vfov() - 26.991).abs() < 1e-3);
+        assert!((settings.aperture() - 0.00625).abs() < 1e-12);
+        assert!((settings.exposure() - 1.0).abs() < 1e-12);
+
+        // One more stop of shutter, ISO and aperture each double the light
+        let brighter = PhysicalSettings {
+            shutter_speed: 1.0 / 50.0,
+            iso: 200.0,
+            f_stop: 8.0 / 2f64.sqrt(),
+            ..settings
+        };
+        assert!((brighter.exposure() - 8.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rays_are_cast_while_the_shutter_is_open_and_through_the_lens() {
+        seed_random(2);
+        let settings = PhysicalSettings {
+            shutter_open: 2.0,
+            shutter_speed: 0.5,
+            iso: 400.0,
+            ..PhysicalSettings::default()
+        };
+        let camera = PhysicalCamera::create(
+            Point3::zero(),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            3.0,
+            settings,
+        );
+        assert_eq!(camera.exposure(), settings.exposure());
+        for _ in 0..100 {
+            let rays = camera.get_weighted_rays(0.5, 0.5);
+            assert_eq!(rays.len(), 1);
+            let (ray, weight) = &rays[0];
+            assert!((2.0..=2.5).contains(&ray.time), "{}", ray.time);
+            assert!(ray.origin.length() <= settings.aperture() / 2.0);
+            assert_eq!(*weight, Color::new(1.0, 1.0, 1.0) * settings.exposure());
+        }
+    }
+}
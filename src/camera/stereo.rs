@@ -0,0 +1,278 @@
+use crate::{
+    camera::{basis, Camera, PerspectiveCamera},
+    color::Color,
+    ray::Ray,
+    vec3::{Point3, Vec3},
+    PI,
+};
+
+/// Represents how the images of both eyes are placed in the final image
+#[derive(Clone, Copy)]
+pub enum StereoLayout {
+    /// Left eye on the left half and right eye on the right half
+    SideBySide,
+    /// Left eye on the top half and right eye on the bottom half
+    OverUnder,
+}
+
+/// Represents the parameters shared by every stereo camera
+#[derive(Clone, Copy)]
+pub struct Stereo {
+    /// Distance between the eyes, in world units
+    interocular_distance: f64,
+    /// Distance at which objects appear at the depth of the screen (zero parallax)
+    convergence_distance: f64,
+    /// How the images of both eyes are placed in the final image
+    layout: StereoLayout,
+}
+
+impl Stereo {
+    /// Returns the stereo parameters
+    ///
+    /// # Arguments
+    ///
+    /// * interocular_distance - distance between the eyes, around 0.064 for a human at meter scale
+    /// * convergence_distance - distance at which objects have no parallax, `f64::INFINITY` keeps the eyes parallel
+    /// * layout - how the images of both eyes are placed in the final image
+    pub fn new(interocular_distance: f64, convergence_distance: f64, layout: StereoLayout) -> Self {
+        Self {
+            interocular_distance,
+            convergence_distance,
+            layout,
+        }
+    }
+}
+
+/// Represents a camera that renders the left and right eye views into the same image for VR
+pub struct StereoCamera {
+    left: Box<dyn Camera>,
+    right: Box<dyn Camera>,
+    layout: StereoLayout,
+}
+
+impl StereoCamera {
+    /// Creates a stereo camera made of two perspective cameras with parallel axes and off-axis
+    /// frustums that meet at the convergence distance, which is also the distance in focus
+    ///
+    /// # Arguments
+    ///
+    /// * lookfrom - position of the point between the eyes
+    /// * lookat - point where the camera is looking at
+    /// * vup - vector representing the up orientation
+    /// * vfov - vertical field of view of each eye in degress
+    /// * aspect_ratio - aspect ratio (width/height) of each eye, the final image is twice as wide
+    ///   (side by side) or twice as tall (over under)
+    /// * aperture - radius of the lens
+    /// * stereo - the stereo parameters
+    pub fn perspective(
+        lookfrom: Point3,
+        lookat: Point3,
+        vup: Vec3,
+        vfov: f64,
+        aspect_ratio: f64,
+        aperture: f64,
+        stereo: Stereo,
+    ) -> StereoCamera {
+        let (u, _, _) = basis(lookfrom, lookat, vup);
+        let focus_dist = if stereo.convergence_distance.is_finite() {
+            stereo.convergence_distance
+        } else {
+            (lookat - lookfrom).length()
+        };
+
+        let eye = |side: f64| {
+            let offset = u * (side * stereo.interocular_distance / 2.0);
+            let mut camera = PerspectiveCamera::create(
+                lookfrom + offset,
+                lookat + offset,
+                vup,
+                vfov,
+                aspect_ratio,
+                aperture,
+                focus_dist,
+            );
+            // Moves the view window back so both windows match at the convergence distance
+            if stereo.convergence_distance.is_finite() {
+                camera.shift_view(-offset);
+            }
+            Box::new(camera)
+        };
+
+        StereoCamera {
+            left: eye(-1.0),
+            right: eye(1.0),
+            layout: stereo.layout,
+        }
+    }
+
+    /// Creates an omnidirectional stereo camera for 360 panoramas, each eye is an
+    /// equirectangular projection whose rays start on a circle with the interocular distance
+    /// as diameter, so every viewing direction has the right parallax
+    ///
+    /// # Arguments
+    ///
+    /// * lookfrom - center of the circle of the eyes
+    /// * lookat - point that will be at the center of each eye's image
+    /// * vup - vector representing the up orientation
+    /// * stereo - the stereo parameters
+    pub fn omnidirectional(
+        lookfrom: Point3,
+        lookat: Point3,
+        vup: Vec3,
+        stereo: Stereo,
+    ) -> StereoCamera {
+        let (u, v, w) = basis(lookfrom, lookat, vup);
+        let eye = |side: f64| {
+            Box::new(OmnidirectionalEye {
+                center: lookfrom,
+                u,
+                v,
+                forward: -w,
+                offset: side * stereo.interocular_distance / 2.0,
+                convergence_distance: stereo.convergence_distance,
+            })
+        };
+
+        StereoCamera {
+            left: eye(-1.0),
+            right: eye(1.0),
+            layout: stereo.layout,
+        }
+    }
+}
+
+impl Camera for StereoCamera {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        match self.layout {
+            StereoLayout::SideBySide if s < 0.5 => self.left.get_ray(2.0 * s, t),
+            StereoLayout::SideBySide => self.right.get_ray(2.0 * s - 1.0, t),
+            StereoLayout::OverUnder if t >= 0.5 => self.left.get_ray(s, 2.0 * t - 1.0),
+            StereoLayout::OverUnder => self.right.get_ray(s, 2.0 * t),
+        }
+    }
+
+    fn get_weighted_rays(&self, s: f64, t: f64) -> Vec<(Ray, Color)> {
+        match self.layout {
+            StereoLayout::SideBySide if s < 0.5 => self.left.get_weighted_rays(2.0 * s, t),
+            StereoLayout::SideBySide => self.right.get_weighted_rays(2.0 * s - 1.0, t),
+            StereoLayout::OverUnder if t >= 0.5 => self.left.get_weighted_rays(s, 2.0 * t - 1.0),
+            StereoLayout::OverUnder => self.right.get_weighted_rays(s, 2.0 * t),
+        }
+    }
+}
+
+/// Represents one eye of an omnidirectional stereo camera
+struct OmnidirectionalEye {
+    /// Center of the circle of the eyes
+    center: Point3,
+    /// Unitary vector that represents the camera's x axis
+    u: Vec3,
+    /// Unitary vector that represents the camera's y axis
+    v: Vec3,
+    /// Unitary vector that points to the center of the image
+    forward: Vec3,
+    /// Signed distance from the center to the eye, negative for the left eye
+    offset: f64,
+    /// Distance at which the rays of both eyes meet
+    convergence_distance: f64,
+}
+
+impl Camera for OmnidirectionalEye {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        let longitude = (s - 0.5) * 2.0 * PI;
+        let latitude = (t - 0.5) * PI;
+
+        let horizontal = self.u * longitude.sin() + self.forward * longitude.cos();
+        let right = self.u * longitude.cos() - self.forward * longitude.sin();
+        let mut direction = horizontal * latitude.cos() + self.v * latitude.sin();
+
+        let origin = self.center + right * self.offset;
+        if self.convergence_distance.is_finite() {
+            direction = self.center + direction * self.convergence_distance - origin;
+        }
+
+        Some(Ray::create(origin, direction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::tests::assert_close;
+
+    /// Returns a stereo camera at the origin looking along -z with eyes 0.064 apart
+    fn perspective(convergence_distance: f64, layout: StereoLayout) -> StereoCamera {
+        StereoCamera::perspective(
+            Point3::zero(),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            90.0,
+            1.0,
+            0.0,
+            Stereo::new(0.064, convergence_distance, layout),
+        )
+    }
+
+    /// Returns the point in focus of the ray through the point of the image
+    fn target(camera: &impl Camera, s: f64, t: f64) -> Point3 {
+        let ray = camera.get_ray(s, t).unwrap();
+        ray.origin + ray.dir
+    }
+
+    #[test]
+    fn each_half_of_the_image_is_an_eye() {
+        let camera = perspective(2.0, StereoLayout::SideBySide);
+        assert_close(
+            camera.get_ray(0.25, 0.5).unwrap().origin,
+            Point3::new(-0.032, 0.0, 0.0),
+        );
+        assert_close(
+            camera.get_ray(0.75, 0.5).unwrap().origin,
+            Point3::new(0.032, 0.0, 0.0),
+        );
+
+        let camera = perspective(2.0, StereoLayout::OverUnder);
+        assert_close(
+            camera.get_ray(0.5, 0.75).unwrap().origin,
+            Point3::new(-0.032, 0.0, 0.0),
+        );
+        assert_close(
+            camera.get_ray(0.5, 0.25).unwrap().origin,
+            Point3::new(0.032, 0.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn eyes_meet_at_the_convergence_distance() {
+        let camera = perspective(2.0, StereoLayout::SideBySide);
+        assert_close(target(&camera, 0.25, 0.5), Point3::new(0.0, 0.0, -2.0));
+        assert_close(target(&camera, 0.75, 0.5), Point3::new(0.0, 0.0, -2.0));
+        // The corners of both views also match at that distance
+        assert_close(target(&camera, 0.0, 1.0), target(&camera, 0.5, 1.0));
+
+        // Without convergence the eyes look in parallel
+        let camera = perspective(f64::INFINITY, StereoLayout::SideBySide);
+        let left = camera.get_ray(0.25, 0.5).unwrap();
+        let right = camera.get_ray(0.75, 0.5).unwrap();
+        assert_close(Vec3::unit_vector(&left.dir), Vec3::new(0.0, 0.0, -1.0));
+        assert_close(Vec3::unit_vector(&right.dir), Vec3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn omnidirectional_eyes_circle_the_center() {
+        let camera = StereoCamera::omnidirectional(
+            Point3::zero(),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Stereo::new(0.064, 2.0, StereoLayout::SideBySide),
+        );
+        // The eyes are to the sides of every viewing direction, so when looking right along +x
+        // the right eye is towards +z
+        let forward = camera.get_ray(0.25, 0.5).unwrap();
+        assert_close(forward.origin, Point3::new(-0.032, 0.0, 0.0));
+        assert_close(forward.origin + forward.dir, Point3::new(0.0, 0.0, -2.0));
+        let right = camera.get_ray(0.875, 0.5).unwrap();
+        assert_close(right.origin, Point3::new(0.0, 0.0, 0.032));
+        assert_close(right.origin + right.dir, Point3::new(2.0, 0.0, 0.0));
+    }
+}
@@ -1,7 +1,11 @@
 use crate::clamp;
 pub use crate::vec3::Vec3;
 pub type Color = Vec3;
-use std::{fs::File, io::Write};
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    path::Path,
+};
 
 /// Writes to a file the color of the pixel
 ///
@@ -11,21 +15,186 @@ use std::{fs::File, io::Write};
 /// * samples_per_pixel - Amount of rays that were cast for this pixel
 /// * file - PPM file that will be writen the pixel coler
 pub fn write_color(color: &Color, samples_per_pixel: i32, mut file: &File) {
-    let mut r = color.x;
-    let mut g = color.y;
-    let mut b = color.z;
+    let [r, g, b] = to_rgb8(color, samples_per_pixel);
 
-    let scale = 1.0 / samples_per_pixel as f64;
-    r = (scale * r).sqrt();
-    g = (scale * g).sqrt();
-    b = (scale * b).sqrt();
-
-    let color_str = format!(
-        "{} {} {}\n",
-        (255.999 * clamp(r, 0.0, 0.9999)) as i32,
-        (255.999 * clamp(g, 0.0, 0.9999)) as i32,
-        (255.999 * clamp(b, 0.0, 0.9999)) as i32
-    );
+    let color_str = format!("{} {} {}\n", r, g, b);
     file.write_all(color_str.as_bytes())
         .expect("Error in writing color");
 }
+
+/// Returns the 8 bit components of the pixel's color, averaged and gamma corrected
+///
+/// # Arguments
+///
+/// * color - The sum of the colors of the pixel
+/// * samples_per_pixel - Amount of rays that were cast for this pixel
+pub fn to_rgb8(color: &Color, samples_per_pixel: i32) -> [u8; 3] {
+    let scale = 1.0 / samples_per_pixel as f64;
+    let component = |c: f64| (255.999 * clamp((scale * c).sqrt(), 0.0, 0.9999)) as u8;
+
+    [component(color.x), component(color.y), component(color.z)]
+}
+
+/// Writes 8 bit pixels to a ppm file
+///
+/// # Arguments
+///
+/// * image_name - The name of the file, without the `.ppm` extension
+/// * width - Width of the image in pixels
+/// * height - Height of the image in pixels
+/// * rgb - The components of the pixels from the top left to the bottom right corner
+pub fn write_ppm(image_name: &str, width: usize, height: usize, rgb: &[u8]) -> io::Result<()> {
+    let mut contents = format!("P3\n{} {}\n255\n", width, height);
+    for pixel in rgb.chunks(3) {
+        contents.push_str(&format!("{} {} {}\n", pixel[0], pixel[1], pixel[2]));
+    }
+    File::create(format!("{}.ppm", image_name))?.write_all(contents.as_bytes())
+}
+
+/// Returns the relative luminance of a linear color, with the Rec. 709 weights
+pub fn luminance(color: &Color) -> f64 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+/// Reads a PPM file (P3 or P6) and returns its width, its height and its pixels, from the top
+/// left to the bottom right corner, with the components between 0 and 1
+///
+/// # Arguments
+///
+/// * path - Path of the PPM file
+pub fn read_ppm(path: &Path) -> io::Result<(usize, usize, Vec<Color>)> {
+    let mut bytes = Vec::new();
+    File::open(path)?.read_to_end(&mut bytes)?;
+
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+    // Reads the header tokens, skipping whitespace and comments
+    let mut pos = 0;
+    let mut header = Vec::new();
+    while header.len() < 4 {
+        while pos < bytes.len() && (bytes[pos].is_ascii_whitespace() || bytes[pos] == b'#') {
+            if bytes[pos] == b'#' {
+                while pos < bytes.len() && bytes[pos] != b'\n' {
+                    pos += 1;
+                }
+            } else {
+                pos += 1;
+            }
+        }
+        let start = pos;
+        while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if start == pos {
+            return Err(invalid("incomplete PPM header"));
+        }
+        header.push(String::from_utf8_lossy(&bytes[start..pos]).to_string());
+    }
+
+    let parse = |s: &str| {
+        s.parse::<usize>()
+            .map_err(|_| invalid("invalid PPM header"))
+    };
+    let width = parse(&header[1])?;
+    let height = parse(&header[2])?;
+    let max = parse(&header[3])?.max(1) as f64;
+    let count = width * height * 3;
+
+    let values: Vec<f64> = match header[0].as_str() {
+        "P3" => String::from_utf8_lossy(&bytes[pos..])
+            .split_ascii_whitespace()
+            .take(count)
+            .map(|v| v.parse::<f64>().map_err(|_| invalid("invalid PPM pixel")))
+            .collect::<io::Result<_>>()?,
+        "P6" => {
+            // A single whitespace separates the header from the binary data
+            let data = &bytes[(pos + 1).min(bytes.len())..];
+            if max < 256.0 {
+                data.iter().take(count).map(|&b| b as f64).collect()
+            } else {
+                data.chunks_exact(2)
+                    .take(count)
+                    .map(|b| u16::from_be_bytes([b[0], b[1]]) as f64)
+                    .collect()
+            }
+        }
+        _ => return Err(invalid("unsupported PPM format")),
+    };
+
+    if values.len() < count {
+        return Err(invalid("missing PPM pixels"));
+    }
+
+    let pixels = values
+        .chunks_exact(3)
+        .map(|c| Color::new(c[0] / max, c[1] / max, c[2] / max))
+        .collect();
+    Ok((width, height, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, path::PathBuf, process};
+
+    /// Writes the bytes to a file in the temporary directory and returns its path
+    fn temporary_file(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = env::temp_dir().join(format!("ray_tracer_{}_{}", process::id(), name));
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    /// Reads the PPM file made of the bytes
+    fn read(name: &str, bytes: &[u8]) -> io::Result<(usize, usize, Vec<Color>)> {
+        let path = temporary_file(name, bytes);
+        let read = read_ppm(&path);
+        fs::remove_file(&path).unwrap();
+        read
+    }
+
+    #[test]
+    fn reads_text_ppm_files_with_comments() {
+        let (width, height, pixels) = read(
+            "text.ppm",
+            b"P3\n# made by hand\n2 1 # size\n4\n4 2 0\n0 0 4\n",
+        )
+        .unwrap();
+        assert_eq!((width, height), (2, 1));
+        assert_eq!(
+            pixels,
+            vec![Color::new(1.0, 0.5, 0.0), Color::new(0.0, 0.0, 1.0)]
+        );
+    }
+
+    #[test]
+    fn reads_binary_ppm_files() {
+        let mut bytes = b"P6 1 2 255\n".to_vec();
+        bytes.extend_from_slice(&[255, 0, 51, 0, 255, 0]);
+        let (width, height, pixels) = read("binary.ppm", &bytes).unwrap();
+        assert_eq!((width, height), (1, 2));
+        assert_eq!(
+            pixels,
+            vec![Color::new(1.0, 0.0, 0.2), Color::new(0.0, 1.0, 0.0)]
+        );
+
+        let mut bytes = b"P6 1 1 65535\n".to_vec();
+        bytes.extend_from_slice(&[255, 255, 0, 0, 128, 0]);
+        let (_, _, pixels) = read("binary16.ppm", &bytes).unwrap();
+        assert_eq!(pixels[0].x, 1.0);
+        assert_eq!(pixels[0].y, 0.0);
+        assert!((pixels[0].z - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn invalid_ppm_files_are_rejected() {
+        for (name, bytes) in [
+            ("unsupported.ppm", &b"P5 1 1 255\n\0"[..]),
+            ("truncated.ppm", b"P3 2 2 255\n0 0 0\n"),
+            ("header.ppm", b"P3 2"),
+            ("size.ppm", b"P3 two 2 255\n"),
+        ] {
+            let error = read(name, bytes).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{}", name);
+        }
+    }
+}
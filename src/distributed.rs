@@ -0,0 +1,94 @@
+//! Renders a scene across worker processes, which can run on other machines
+//!
+//! A coordinator splits the image into tiles and sends them over TCP to the workers, which
+//! render them and send back their samples. The scene is sent in the scene file format, so
+//! the workers only need this crate's worker binary and not the code that built the scene.
+//! Since every sample is seeded from the pixel of the full image, the merged image is the
+//! same as a render on a single machine with the same seed.
+//!
+//! The protocol, started by the coordinator after connecting:
+//!
+//! * The coordinator sends `RTDIST02`, then the length of the scene text as a little endian
+//!   u32 and the text
+//! * For each tile the coordinator sends the byte 1 and the x, y, width and height of the tile
+//!   as little endian u32, and the worker answers with the tile's samples in the format of
+//!   `Framebuffer::write_samples`
+//! * The byte 0 ends the connection
+
+mod coordinator;
+mod worker;
+
+pub use {
+    coordinator::{Coordinator, WorkerFailure},
+    worker::{handle_connection, serve},
+};
+
+use crate::{framebuffer::read_u32, renderer::CropWindow};
+use std::io::{self, Read, Write};
+
+/// Bytes that start every connection
+const MAGIC: &[u8; 8] = b"RTDIST02";
+
+/// Longest scene text a worker accepts, in bytes
+const MAX_SCENE_LENGTH: usize = 1 << 26;
+
+/// Byte that announces a tile
+const TILE: u8 = 1;
+
+/// Byte that ends the connection
+const DONE: u8 = 0;
+
+/// Writes the start of a connection with the scene
+fn write_scene(writer: &mut impl Write, scene: &str) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&(scene.len() as u32).to_le_bytes())?;
+    writer.write_all(scene.as_bytes())?;
+    writer.flush()
+}
+
+/// Reads the start of a connection and returns the scene text
+fn read_scene(reader: &mut impl Read) -> io::Result<String> {
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid("not a render connection"));
+    }
+
+    let length = read_u32(reader)? as usize;
+    if length > MAX_SCENE_LENGTH {
+        return Err(invalid("the scene is too long"));
+    }
+    let mut text = vec![0; length];
+    reader.read_exact(&mut text)?;
+    String::from_utf8(text).map_err(|_| invalid("the scene isn't valid UTF-8"))
+}
+
+/// Writes a tile to render
+fn write_tile(writer: &mut impl Write, tile: &CropWindow) -> io::Result<()> {
+    writer.write_all(&[TILE])?;
+    for value in [tile.x, tile.y, tile.width, tile.height] {
+        writer.write_all(&(value as u32).to_le_bytes())?;
+    }
+    writer.flush()
+}
+
+/// Reads the next tile to render, None when the connection ends
+fn read_tile(reader: &mut impl Read) -> io::Result<Option<CropWindow>> {
+    let mut tag = [0; 1];
+    reader.read_exact(&mut tag)?;
+    match tag[0] {
+        DONE => Ok(None),
+        TILE => Ok(Some(CropWindow::new(
+            read_u32(reader)? as usize,
+            read_u32(reader)? as usize,
+            read_u32(reader)? as usize,
+            read_u32(reader)? as usize,
+        ))),
+        _ => Err(invalid("unknown message")),
+    }
+}
+
+/// Returns an `InvalidData` error with the message
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
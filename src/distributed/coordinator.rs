@@ -0,0 +1,220 @@
+use crate::{
+    distributed::{write_scene, write_tile, DONE},
+    framebuffer::Framebuffer,
+    renderer::{CropWindow, Goal, ProgressObserver, ProgressTracker},
+    scene::Scene,
+};
+use std::{
+    io::{self, BufReader, BufWriter, Write},
+    net::{TcpStream, ToSocketAddrs},
+    rc::Rc,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+/// Longest time to wait for a connection to a worker
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Represents what a worker's thread tells the coordinator
+enum Message {
+    /// A tile was rendered
+    Rendered(CropWindow, Framebuffer),
+    /// The worker failed, its tile went back to the queue
+    Failed(String, io::Error),
+}
+
+/// Represents a worker that failed during a render, its tiles went to the others
+#[derive(Debug)]
+pub struct WorkerFailure {
+    /// Address of the worker
+    pub address: String,
+    /// Why the worker failed
+    pub error: io::Error,
+}
+
+/// Splits renders into tiles and distributes them among worker processes
+pub struct Coordinator {
+    /// Addresses of the workers, like `127.0.0.1:7878`
+    workers: Vec<String>,
+    /// Width and height of the tiles in pixels
+    tile_size: usize,
+    /// Longest time to wait for a worker to read a message or to send the samples of a tile,
+    /// after which the worker is considered failed
+    timeout: Duration,
+    /// Receives the progress of the render, as the samples of the tiles come back
+    observer: Option<Rc<dyn ProgressObserver>>,
+}
+
+impl Coordinator {
+    /// Returns a coordinator that sends 64x64 pixel tiles to the given workers, waits up to 5
+    /// minutes for each tile and doesn't report the progress
+    ///
+    /// # Arguments
+    ///
+    /// * workers - Addresses of the workers, like `127.0.0.1:7878`
+    pub fn new(workers: Vec<String>) -> Self {
+        Self {
+            workers,
+            tile_size: 64,
+            timeout: Duration::from_secs(300),
+            observer: None,
+        }
+    }
+
+    /// Returns the coordinator with tiles of the given width and height in pixels
+    pub fn with_tile_size(mut self, tile_size: usize) -> Self {
+        self.tile_size = tile_size.max(1);
+        self
+    }
+
+    /// Returns the coordinator with the given longest time to wait for a worker to render a
+    /// tile, a worker that takes longer fails and its tile goes to the others
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout.max(Duration::from_millis(1));
+        self
+    }
+
+    /// Returns the coordinator with the given observer of the progress of its renders
+    pub fn with_observer(mut self, observer: Rc<dyn ProgressObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Renders the scene on the workers and returns the merged samples and the workers that
+    /// failed
+    ///
+    /// Each worker gets a new tile as soon as it sends the last one back, so faster workers
+    /// render more tiles. The tiles of a worker that fails, or that doesn't answer within the
+    /// timeout, go to the others, the render fails only if every worker does.
+    ///
+    /// # Arguments
+    ///
+    /// * scene - The scene to render, with its camera and its settings
+    pub fn render(&self, scene: &Scene) -> io::Result<(Framebuffer, Vec<WorkerFailure>)> {
+        let (width, height) = (scene.image_width as usize, scene.image_height as usize);
+        let mut tiles = Vec::new();
+        for y in (0..height).step_by(self.tile_size) {
+            for x in (0..width).step_by(self.tile_size) {
+                tiles.push(
+                    CropWindow::new(x, y, self.tile_size, self.tile_size).clip(width, height),
+                );
+            }
+        }
+        // Tiles are popped from the end, this way the top of the image is rendered first
+        tiles.reverse();
+        let total = tiles.len();
+
+        let queue = Arc::new(Mutex::new(tiles));
+        let text = Arc::new(scene.to_string());
+        let mut framebuffer = Framebuffer::new(width, height).with_filter(scene.filter);
+        let mut rendered = 0;
+        let mut workers = self.workers.clone();
+        let mut failures = Vec::new();
+        let target = (width * height) as u64 * scene.samples_per_pixel as u64;
+        let mut tracker =
+            ProgressTracker::new(self.observer.clone(), Goal::Samples { done: 0, target });
+
+        // A tile of a worker that fails can go back to the queue after the others have left,
+        // so the workers that are still fine are called again until every tile is rendered
+        while rendered < total && !workers.is_empty() {
+            let (sender, receiver) = mpsc::channel();
+            for address in &workers {
+                let (address, queue, text) = (address.clone(), queue.clone(), text.clone());
+                let (sender, timeout) = (sender.clone(), self.timeout);
+                thread::spawn(move || {
+                    if let Err(e) = work(&address, timeout, &queue, &text, &sender) {
+                        let _ = sender.send(Message::Failed(address, e));
+                    }
+                });
+            }
+            drop(sender);
+
+            for message in receiver {
+                match message {
+                    Message::Rendered(tile, samples) => {
+                        framebuffer.merge(&samples, tile.x, tile.y);
+                        rendered += 1;
+                        tracker.add_samples(samples.total_samples());
+                    }
+                    Message::Failed(address, error) => {
+                        workers.retain(|worker| *worker != address);
+                        failures.push(WorkerFailure { address, error });
+                    }
+                }
+            }
+        }
+
+        if rendered < total {
+            return Err(match failures.pop() {
+                Some(failure) => failure.error,
+                None => io::Error::other("there are no workers"),
+            });
+        }
+        tracker.finish();
+        Ok((framebuffer, failures))
+    }
+}
+
+/// Sends tiles from the queue to a worker until the queue is empty, a tile whose samples
+/// don't come back is put back in the queue
+///
+/// # Arguments
+///
+/// * address - Address of the worker
+/// * timeout - Longest time to wait for the worker to read a message or to send the samples of
+///   a tile
+/// * queue - Tiles that are still to be rendered
+/// * scene - The scene in the scene file format
+/// * sender - Where the rendered tiles are sent
+fn work(
+    address: &str,
+    timeout: Duration,
+    queue: &Mutex<Vec<CropWindow>>,
+    scene: &str,
+    sender: &mpsc::Sender<Message>,
+) -> io::Result<()> {
+    let stream = connect(address)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+    write_scene(&mut writer, scene)?;
+
+    loop {
+        let tile = match queue.lock().unwrap().pop() {
+            Some(tile) => tile,
+            None => break,
+        };
+
+        let samples = write_tile(&mut writer, &tile)
+            .and_then(|_| Framebuffer::read_samples(&mut reader, tile.width, tile.height));
+        match samples {
+            Ok(samples) => {
+                let _ = sender.send(Message::Rendered(tile, samples));
+            }
+            Err(e) => {
+                queue.lock().unwrap().push(tile);
+                return Err(e);
+            }
+        }
+    }
+
+    writer.write_all(&[DONE])?;
+    writer.flush()
+}
+
+/// Connects to a worker, trying each address the name resolves to for at most
+/// `CONNECT_TIMEOUT`
+fn connect(address: &str) -> io::Result<TcpStream> {
+    let mut last_error = None;
+    for address in address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "the address doesn't resolve")
+    }))
+}
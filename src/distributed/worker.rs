@@ -0,0 +1,63 @@
+use crate::{
+    distributed::{read_scene, read_tile},
+    renderer::{render, RenderSettings},
+    scene::Scene,
+};
+use std::{
+    io::{BufReader, BufWriter, Write},
+    net::{TcpListener, TcpStream},
+    time::Duration,
+};
+
+/// Longest time to wait for a coordinator to send a message or to read the samples of a tile,
+/// after which the connection fails so a coordinator that went away doesn't hold the worker
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Renders the tiles sent by coordinators, one connection at a time, until the listener fails
+///
+/// A connection that fails is reported to the standard error and the worker waits for the next.
+///
+/// # Arguments
+///
+/// * listener - Where the coordinators connect
+pub fn serve(listener: &TcpListener) -> std::io::Result<()> {
+    loop {
+        let (stream, address) = listener.accept()?;
+        if let Err(e) = handle_connection(stream) {
+            eprintln!("connection with {} failed: {}", address, e);
+        }
+    }
+}
+
+/// Reads the scene sent by a coordinator and renders the tiles it asks for until it ends the
+/// connection
+///
+/// # Arguments
+///
+/// * stream - The connection with the coordinator
+pub fn handle_connection(stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+
+    let scene = Scene::parse(&read_scene(&mut reader)?)?;
+    let world = scene.world();
+    let camera = scene.camera();
+    let (width, height) = (
+        scene.image_width.max(0) as usize,
+        scene.image_height.max(0) as usize,
+    );
+
+    while let Some(tile) = read_tile(&mut reader)? {
+        let tile = tile.try_clip(width, height)?;
+        let settings = RenderSettings {
+            crop: Some(tile),
+            observer: None,
+            ..scene.settings()
+        };
+        render(&settings, &camera, &world)?.write_samples(&mut writer)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,299 @@
+use crate::PI;
+use std::{fmt, io, str::FromStr};
+
+/// Largest radius of a filter in pixels, so a sample is splatted to at most 17x17 pixels
+pub const MAX_RADIUS: f64 = 8.0;
+
+/// Represents the shape of a pixel reconstruction filter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterKind {
+    /// Every sample closer than the radius weighs the same
+    Box,
+    /// The weight falls linearly to 0 at the radius
+    Tent,
+    /// A gaussian with a standard deviation of a third of the radius, shifted to be 0 at the
+    /// radius
+    Gaussian,
+    /// The cubic of Mitchell and Netravali with B = C = 1/3, it has small negative lobes that
+    /// sharpen the edges
+    Mitchell,
+    /// The 4 term Blackman-Harris window, smooth like a gaussian but with less blur
+    BlackmanHarris,
+}
+
+impl FilterKind {
+    /// Returns the name of the filter, as written in scene files and in the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            FilterKind::Box => "box",
+            FilterKind::Tent => "tent",
+            FilterKind::Gaussian => "gaussian",
+            FilterKind::Mitchell => "mitchell",
+            FilterKind::BlackmanHarris => "blackman_harris",
+        }
+    }
+
+    /// Returns the usual radius of the filter in pixels
+    pub fn default_radius(&self) -> f64 {
+        match self {
+            FilterKind::Box => 0.5,
+            FilterKind::Tent => 1.0,
+            FilterKind::Gaussian | FilterKind::BlackmanHarris => 1.5,
+            FilterKind::Mitchell => 2.0,
+        }
+    }
+}
+
+impl FromStr for FilterKind {
+    type Err = io::Error;
+
+    fn from_str(name: &str) -> io::Result<Self> {
+        match name {
+            "box" => Ok(FilterKind::Box),
+            "tent" => Ok(FilterKind::Tent),
+            "gaussian" => Ok(FilterKind::Gaussian),
+            "mitchell" => Ok(FilterKind::Mitchell),
+            "blackman_harris" => Ok(FilterKind::BlackmanHarris),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown filter {}", name),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for FilterKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Represents how the samples are weighted to reconstruct the pixels, each sample is added to
+/// every pixel whose center is closer than the radius in x and in y
+///
+/// The default, a box of radius 0.5, only adds a sample to the pixel it was cast through, so
+/// each pixel is the average of its samples.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Filter {
+    /// Shape of the filter
+    kind: FilterKind,
+    /// Distance in pixels at which the weight of a sample reaches 0
+    radius: f64,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self {
+            kind: FilterKind::Box,
+            radius: 0.5,
+        }
+    }
+}
+
+impl Filter {
+    /// Returns a filter of the given shape and radius, or an `InvalidInput` error if the
+    /// radius isn't over 0 and at most `MAX_RADIUS`
+    ///
+    /// # Arguments
+    ///
+    /// * kind - Shape of the filter
+    /// * radius - Distance in pixels at which the weight of a sample reaches 0
+    pub fn new(kind: FilterKind, radius: f64) -> io::Result<Self> {
+        if !(radius > 0.0 && radius <= MAX_RADIUS) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "the radius of the filter must be over 0 and at most {} pixels, got {}",
+                    MAX_RADIUS, radius
+                ),
+            ));
+        }
+        Ok(Self { kind, radius })
+    }
+
+    /// Returns the shape of the filter
+    pub fn kind(&self) -> FilterKind {
+        self.kind
+    }
+
+    /// Returns the distance in pixels at which the weight of a sample reaches 0
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    /// Returns the number of pixels around a pixel its samples can reach
+    pub fn reach(&self) -> usize {
+        (self.radius - 0.5).max(0.0).ceil() as usize
+    }
+
+    /// Returns the weight of a sample for a pixel
+    ///
+    /// # Arguments
+    ///
+    /// * dx - Distance in pixels from the center of the pixel to the sample in x
+    /// * dy - Distance in pixels from the center of the pixel to the sample in y
+    pub fn weight(&self, dx: f64, dy: f64) -> f64 {
+        self.evaluate(dx) * self.evaluate(dy)
+    }
+
+    /// Returns the weight of the filter at a distance along one axis, the filters are separable
+    fn evaluate(&self, d: f64) -> f64 {
+        let r = self.radius;
+        let d = d.abs();
+        if d > r {
+            return 0.0;
+        }
+        match self.kind {
+            FilterKind::Box => 1.0,
+            FilterKind::Tent => 1.0 - d / r,
+            FilterKind::Gaussian => {
+                let gaussian = |x: f64| (-4.5 * (x / r).powi(2)).exp();
+                gaussian(d) - gaussian(r)
+            }
+            FilterKind::Mitchell => {
+                let (b, c) = (1.0 / 3.0, 1.0 / 3.0);
+                let x = 2.0 * d / r;
+                if x < 1.0 {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x.powi(3)
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x.powi(2)
+                        + (6.0 - 2.0 * b))
+                        / 6.0
+                } else {
+                    ((-b - 6.0 * c) * x.powi(3)
+                        + (6.0 * b + 30.0 * c) * x.powi(2)
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c))
+                        / 6.0
+                }
+            }
+            FilterKind::BlackmanHarris => {
+                let t = 2.0 * PI * (d / r + 1.0) / 2.0;
+                0.35875 - 0.48829 * t.cos() + 0.14128 * (2.0 * t).cos() - 0.01168 * (3.0 * t).cos()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color::Color, framebuffer::Framebuffer};
+
+    const KINDS: [FilterKind; 5] = [
+        FilterKind::Box,
+        FilterKind::Tent,
+        FilterKind::Gaussian,
+        FilterKind::Mitchell,
+        FilterKind::BlackmanHarris,
+    ];
+
+    /// Returns the filter of the kind with its usual radius
+    fn usual(kind: FilterKind) -> Filter {
+        Filter::new(kind, kind.default_radius()).unwrap()
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for kind in KINDS {
+            assert_eq!(kind.to_string().parse::<FilterKind>().unwrap(), kind);
+        }
+        assert!("lanczos".parse::<FilterKind>().is_err());
+    }
+
+    #[test]
+    fn radii_outside_the_limits_are_rejected() {
+        for radius in [0.0, -0.5, MAX_RADIUS + 0.1, f64::INFINITY, f64::NAN] {
+            let error = Filter::new(FilterKind::Box, radius).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+        assert!(Filter::new(FilterKind::Box, MAX_RADIUS).is_ok());
+    }
+
+    #[test]
+    fn reach_covers_the_pixels_within_the_radius() {
+        let reach = |kind: FilterKind, radius: f64| Filter::new(kind, radius).unwrap().reach();
+        assert_eq!(Filter::default().reach(), 0);
+        assert_eq!(reach(FilterKind::Tent, 1.0), 1);
+        assert_eq!(reach(FilterKind::Gaussian, 1.5), 1);
+        assert_eq!(reach(FilterKind::Gaussian, 1.6), 2);
+        assert_eq!(reach(FilterKind::Mitchell, 2.0), 2);
+        assert_eq!(reach(FilterKind::Box, 0.25), 0);
+        assert_eq!(reach(FilterKind::Box, MAX_RADIUS), 8);
+
+        // A sample at the edge of its pixel is still within the radius of the last pixel
+        // reached, and beyond the radius of the next one
+        for kind in KINDS {
+            let filter = usual(kind);
+            let reach = filter.reach() as f64;
+            assert!(reach - 0.5 < filter.radius());
+            assert!(reach + 0.5 >= filter.radius());
+        }
+    }
+
+    #[test]
+    fn weights_are_zero_beyond_the_radius() {
+        for kind in KINDS {
+            let filter = usual(kind);
+            let r = filter.radius();
+            assert_eq!(filter.weight(r + 0.01, 0.0), 0.0, "{}", kind);
+            assert_eq!(filter.weight(0.0, -r - 0.01), 0.0, "{}", kind);
+            // The filters other than the box fall to 0 at the radius, the terms of the
+            // Blackman-Harris window leave 6e-5
+            if kind != FilterKind::Box {
+                assert!(filter.weight(r, 0.0).abs() < 1e-4, "{}", kind);
+            }
+        }
+    }
+
+    #[test]
+    fn weights_are_symmetric_and_largest_at_the_center() {
+        for kind in KINDS {
+            let filter = usual(kind);
+            let center = filter.weight(0.0, 0.0);
+            assert!(center > 0.0);
+            for (dx, dy) in [(0.3, 0.1), (0.7, -0.4), (1.2, 0.9)] {
+                let weight = filter.weight(dx, dy);
+                assert_eq!(weight, filter.weight(-dx, dy), "{}", kind);
+                assert_eq!(weight, filter.weight(dx, -dy), "{}", kind);
+                assert_eq!(weight, filter.weight(dy, dx), "{}", kind);
+                assert!(weight <= center, "{}", kind);
+            }
+        }
+    }
+
+    #[test]
+    fn mitchell_has_negative_lobes() {
+        let filter = usual(FilterKind::Mitchell);
+        let r = filter.radius();
+        assert!(filter.evaluate(0.75 * r) < 0.0);
+        assert!(filter.evaluate(0.25 * r) > 0.0);
+        // The two pieces of the cubic meet at half the radius
+        let (inner, outer) = (
+            filter.evaluate(0.5 * r - 1e-9),
+            filter.evaluate(0.5 * r + 1e-9),
+        );
+        assert!((inner - outer).abs() < 1e-6);
+    }
+
+    #[test]
+    fn splatted_weights_are_normalized() {
+        let color = Color::new(0.2, 0.5, 0.8);
+        let offsets = [(-0.25, -0.25), (0.25, -0.25), (-0.25, 0.25), (0.25, 0.25)];
+        for kind in KINDS {
+            let mut framebuffer = Framebuffer::new(8, 6).with_filter(usual(kind));
+            for y in 0..6 {
+                for x in 0..8 {
+                    for &offset in &offsets {
+                        framebuffer.add_sample(x, y, offset, color);
+                    }
+                }
+            }
+            for y in 0..6 {
+                for x in 0..8 {
+                    let pixel = framebuffer.color(x, y);
+                    assert!((pixel - color).length() < 1e-9, "{} at {},{}", kind, x, y);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,486 @@
+use crate::{
+    color::{luminance, to_rgb8, write_ppm, Color},
+    filter::Filter,
+    png::write_png,
+};
+use std::{
+    io::{self, Read, Write},
+    path::Path,
+};
+
+/// Luminance under which the noise of a pixel is measured relative to this value instead of
+/// the pixel's, so almost black pixels don't need endless samples
+const MIN_LUMINANCE: f64 = 0.01;
+
+/// Represents the image being rendered, accumulating the samples cast through each pixel
+///
+/// Pixels are addressed by (x, y) with x growing to the right and y growing downwards, the
+/// same order they are written in the image files. The samples cast through a pixel are counted
+/// and give its noise, while its color is reconstructed from the samples of the pixels around
+/// it, weighted by the filter.
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    /// How the samples are weighted to reconstruct the pixels
+    filter: Filter,
+    /// Sum of the colors of the samples of each pixel
+    sums: Vec<Color>,
+    /// Sum of the colors of the samples that reach each pixel, weighted by the filter
+    weighted_sums: Vec<Color>,
+    /// Sum of the weights of the samples that reach each pixel
+    weights: Vec<f64>,
+    /// Sum of the squared luminances of the samples of each pixel, used to estimate the noise
+    squared_luminances: Vec<f64>,
+    /// Number of samples of each pixel
+    samples: Vec<u32>,
+}
+
+impl Framebuffer {
+    /// Returns a black framebuffer without samples, whose pixels are the average of their
+    /// samples
+    ///
+    /// # Arguments
+    ///
+    /// * width - Width of the image in pixels
+    /// * height - Height of the image in pixels
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            filter: Filter::default(),
+            sums: vec![Color::zero(); width * height],
+            weighted_sums: vec![Color::zero(); width * height],
+            weights: vec![0.0; width * height],
+            squared_luminances: vec![0.0; width * height],
+            samples: vec![0; width * height],
+        }
+    }
+
+    /// Sets how the samples are weighted to reconstruct the pixels, it applies to the samples
+    /// added from then on
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Returns how the samples are weighted to reconstruct the pixels
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+
+    /// Returns the width of the image in pixels
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the image in pixels
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Adds the color of a sample to the pixel it was cast through, and splats it to the pixels
+    /// around it that the filter reaches
+    ///
+    /// # Arguments
+    ///
+    /// * x - Column of the pixel, from the left
+    /// * y - Row of the pixel, from the top
+    /// * offset - Position of the sample relative to the center of the pixel, in pixels to the
+    ///   right and downwards, between -0.5 and 0.5
+    /// * color - Color brought by the sample
+    pub fn add_sample(&mut self, x: usize, y: usize, offset: (f64, f64), color: Color) {
+        let i = y * self.width + x;
+        self.sums[i] += color;
+        self.squared_luminances[i] += luminance(&color).powi(2);
+        self.samples[i] += 1;
+
+        let reach = self.filter.reach();
+        for row in y.saturating_sub(reach)..(y + reach + 1).min(self.height) {
+            for column in x.saturating_sub(reach)..(x + reach + 1).min(self.width) {
+                let weight = self.filter.weight(
+                    column as f64 - x as f64 - offset.0,
+                    row as f64 - y as f64 - offset.1,
+                );
+                if weight != 0.0 {
+                    let j = row * self.width + column;
+                    self.weighted_sums[j] += color * weight;
+                    self.weights[j] += weight;
+                }
+            }
+        }
+    }
+
+    /// Adds the samples of another framebuffer, like the render of a tile or of other passes
+    /// by another process, to the pixels it covers
+    ///
+    /// # Arguments
+    ///
+    /// * other - The samples to add, it must fit in the framebuffer at the given position
+    /// * x - Column of the framebuffer where the left edge of the other one is
+    /// * y - Row of the framebuffer where the top edge of the other one is
+    pub fn merge(&mut self, other: &Framebuffer, x: usize, y: usize) {
+        for row in 0..other.height {
+            for column in 0..other.width {
+                let i = (y + row) * self.width + x + column;
+                let j = row * other.width + column;
+                self.sums[i] += other.sums[j];
+                self.weighted_sums[i] += other.weighted_sums[j];
+                self.weights[i] += other.weights[j];
+                self.squared_luminances[i] += other.squared_luminances[j];
+                self.samples[i] += other.samples[j];
+            }
+        }
+    }
+
+    /// Returns the pixels of a region of the framebuffer, with its filter
+    ///
+    /// # Arguments
+    ///
+    /// * x - Column of the left edge of the region
+    /// * y - Row of the top edge of the region
+    /// * width - Width of the region in pixels, it must fit in the framebuffer
+    /// * height - Height of the region in pixels, it must fit in the framebuffer
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Framebuffer {
+        let mut region = Framebuffer::new(width, height).with_filter(self.filter);
+        for row in 0..height {
+            for column in 0..width {
+                let i = row * width + column;
+                let j = (y + row) * self.width + x + column;
+                region.sums[i] = self.sums[j];
+                region.weighted_sums[i] = self.weighted_sums[j];
+                region.weights[i] = self.weights[j];
+                region.squared_luminances[i] = self.squared_luminances[j];
+                region.samples[i] = self.samples[j];
+            }
+        }
+        region
+    }
+
+    /// Returns the number of samples of a pixel
+    pub fn samples(&self, x: usize, y: usize) -> u32 {
+        self.samples[y * self.width + x]
+    }
+
+    /// Returns the color of a pixel, the average of the samples that reach it weighted by the
+    /// filter, the plain average of its own samples if their weights don't add up to more than
+    /// 0 and black if it has no samples
+    pub fn color(&self, x: usize, y: usize) -> Color {
+        let i = y * self.width + x;
+        if self.weights[i] > 0.0 {
+            self.weighted_sums[i] / self.weights[i]
+        } else if self.samples[i] == 0 {
+            Color::zero()
+        } else {
+            self.sums[i] / self.samples[i] as f64
+        }
+    }
+
+    /// Returns the variance of the luminance of the samples of a pixel, 0 if it has less than
+    /// two samples
+    ///
+    /// Only the samples cast through the pixel are used, not the ones splatted by the filter.
+    pub fn variance(&self, x: usize, y: usize) -> f64 {
+        let i = y * self.width + x;
+        let n = self.samples[i] as f64;
+        if n < 2.0 {
+            return 0.0;
+        }
+        let mean = luminance(&self.sums[i]) / n;
+        ((self.squared_luminances[i] / n - mean * mean) * n / (n - 1.0)).max(0.0)
+    }
+
+    /// Returns the estimated noise of a pixel, the standard error of the mean luminance of its
+    /// samples relative to that luminance, infinite if it has less than two samples
+    pub fn noise(&self, x: usize, y: usize) -> f64 {
+        let n = self.samples(x, y);
+        if n < 2 {
+            return f64::INFINITY;
+        }
+        let standard_error = (self.variance(x, y) / n as f64).sqrt();
+        let mean = luminance(&self.sums[y * self.width + x]) / n as f64;
+        standard_error / mean.max(MIN_LUMINANCE)
+    }
+
+    /// Returns the average noise of the pixels, see `noise`
+    pub fn average_noise(&self) -> f64 {
+        let mut total = 0.0;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                total += self.noise(x, y);
+            }
+        }
+        total / (self.width * self.height) as f64
+    }
+
+    /// Returns the number of samples of all the pixels
+    pub fn total_samples(&self) -> u64 {
+        self.samples.iter().map(|&n| n as u64).sum()
+    }
+
+    /// Returns the average number of samples of the pixels
+    pub fn average_samples(&self) -> f64 {
+        self.total_samples() as f64 / self.samples.len() as f64
+    }
+
+    /// Returns the fewest and the most samples a pixel has
+    pub fn sample_range(&self) -> (u32, u32) {
+        let min = self.samples.iter().copied().min().unwrap_or(0);
+        let max = self.samples.iter().copied().max().unwrap_or(0);
+        (min, max)
+    }
+
+    /// Writes the number of samples of each pixel to a grayscale png file, white being the
+    /// pixels with the most samples
+    ///
+    /// # Arguments
+    ///
+    /// * path - Path of the png file
+    pub fn write_sample_map(&self, path: &Path) -> io::Result<()> {
+        let max = self.sample_range().1.max(1);
+        let rgb: Vec<u8> = self
+            .samples
+            .iter()
+            .flat_map(|&n| [(255 * n / max) as u8; 3])
+            .collect();
+        write_png(path, self.width, self.height, &rgb)
+    }
+
+    /// Writes the size and the accumulated samples of the framebuffer in a binary format, so
+    /// it can be read back with `read_samples` without losing precision
+    ///
+    /// The filter isn't written, only the weighted sums of the samples added so far.
+    ///
+    /// # Arguments
+    ///
+    /// * writer - Where the samples are written
+    pub fn write_samples(&self, writer: &mut impl Write) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(8 + self.samples.len() * 68);
+        bytes.extend_from_slice(&(self.width as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.height as u32).to_le_bytes());
+        for i in 0..self.samples.len() {
+            for value in [
+                self.sums[i].x,
+                self.sums[i].y,
+                self.sums[i].z,
+                self.weighted_sums[i].x,
+                self.weighted_sums[i].y,
+                self.weighted_sums[i].z,
+                self.weights[i],
+                self.squared_luminances[i],
+            ] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            bytes.extend_from_slice(&self.samples[i].to_le_bytes());
+        }
+        writer.write_all(&bytes)
+    }
+
+    /// Reads a framebuffer written by `write_samples`, with the default filter
+    ///
+    /// The size is checked before anything is allocated, so a reader that sends a wrong size
+    /// gets an `InvalidData` error instead of a huge framebuffer.
+    ///
+    /// # Arguments
+    ///
+    /// * reader - Where the samples are read from
+    /// * width - The width in pixels the framebuffer must have
+    /// * height - The height in pixels the framebuffer must have
+    pub fn read_samples(reader: &mut impl Read, width: usize, height: usize) -> io::Result<Self> {
+        let (read_width, read_height) = (read_u32(reader)? as usize, read_u32(reader)? as usize);
+        if (read_width, read_height) != (width, height) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "the samples are {}x{} pixels instead of {}x{}",
+                    read_width, read_height, width, height
+                ),
+            ));
+        }
+        let mut framebuffer = Framebuffer::new(width, height);
+        for i in 0..width * height {
+            framebuffer.sums[i] =
+                Color::new(read_f64(reader)?, read_f64(reader)?, read_f64(reader)?);
+            framebuffer.weighted_sums[i] =
+                Color::new(read_f64(reader)?, read_f64(reader)?, read_f64(reader)?);
+            framebuffer.weights[i] = read_f64(reader)?;
+            framebuffer.squared_luminances[i] = read_f64(reader)?;
+            framebuffer.samples[i] = read_u32(reader)?;
+        }
+        Ok(framebuffer)
+    }
+
+    /// Returns the 8 bit, gamma corrected components of the pixels from the top left to the
+    /// bottom right corner
+    pub fn to_rgb8(&self) -> Vec<u8> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .flat_map(|(x, y)| to_rgb8(&self.color(x, y), 1))
+            .collect()
+    }
+
+    /// Writes the image to a ppm file
+    ///
+    /// # Arguments
+    ///
+    /// * image_name - The name of the file, without the `.ppm` extension
+    pub fn write_ppm(&self, image_name: &str) -> io::Result<()> {
+        write_ppm(image_name, self.width, self.height, &self.to_rgb8())
+    }
+
+    /// Writes the image to a png file
+    ///
+    /// # Arguments
+    ///
+    /// * path - Path of the png file
+    pub fn write_png(&self, path: &Path) -> io::Result<()> {
+        write_png(path, self.width, self.height, &self.to_rgb8())
+    }
+}
+
+/// Reads a little endian u32
+pub(crate) fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// Reads a little endian f64
+pub(crate) fn read_f64(reader: &mut impl Read) -> io::Result<f64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a 3x2 framebuffer with a different number of samples in each pixel
+    fn framebuffer() -> Framebuffer {
+        let mut framebuffer = Framebuffer::new(3, 2);
+        for y in 0..2 {
+            for x in 0..3 {
+                for n in 0..=(y * 3 + x) {
+                    let value = (x + y) as f64 * 0.1 + n as f64 * 0.01;
+                    framebuffer.add_sample(x, y, (0.0, 0.0), Color::new(value, 0.5, 0.25));
+                }
+            }
+        }
+        framebuffer
+    }
+
+    /// Returns true if both framebuffers have the same samples
+    fn same_samples(a: &Framebuffer, b: &Framebuffer) -> bool {
+        let mut bytes_a = Vec::new();
+        let mut bytes_b = Vec::new();
+        a.write_samples(&mut bytes_a).unwrap();
+        b.write_samples(&mut bytes_b).unwrap();
+        bytes_a == bytes_b
+    }
+
+    #[test]
+    fn pixels_are_the_average_of_their_samples() {
+        let mut framebuffer = Framebuffer::new(2, 1);
+        framebuffer.add_sample(0, 0, (0.2, -0.3), Color::new(1.0, 0.0, 0.5));
+        framebuffer.add_sample(0, 0, (-0.4, 0.1), Color::new(0.0, 1.0, 0.5));
+        assert_eq!(framebuffer.color(0, 0), Color::new(0.5, 0.5, 0.5));
+        assert_eq!(framebuffer.samples(0, 0), 2);
+        assert_eq!(framebuffer.color(1, 0), Color::zero());
+        assert_eq!(framebuffer.samples(1, 0), 0);
+        assert_eq!(framebuffer.to_rgb8(), [181, 181, 181, 0, 0, 0]);
+    }
+
+    #[test]
+    fn sample_counts_are_summed() {
+        let framebuffer = framebuffer();
+        assert_eq!(framebuffer.total_samples(), 21);
+        assert_eq!(framebuffer.average_samples(), 3.5);
+        assert_eq!(framebuffer.sample_range(), (1, 6));
+    }
+
+    #[test]
+    fn merged_passes_are_the_same_as_a_single_one() {
+        let mut whole = framebuffer();
+        let second_pass = framebuffer();
+        let mut merged = framebuffer();
+        merged.merge(&second_pass, 0, 0);
+        for y in 0..2 {
+            for x in 0..3 {
+                for n in 0..=(y * 3 + x) {
+                    let value = (x + y) as f64 * 0.1 + n as f64 * 0.01;
+                    whole.add_sample(x, y, (0.0, 0.0), Color::new(value, 0.5, 0.25));
+                }
+            }
+        }
+        // The sums are added in another order, so they may differ in the last bits
+        for y in 0..2 {
+            for x in 0..3 {
+                assert_eq!(merged.samples(x, y), whole.samples(x, y));
+                assert!((merged.color(x, y) - whole.color(x, y)).length() < 1e-12);
+                assert!((merged.variance(x, y) - whole.variance(x, y)).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn regions_are_cropped_and_merged_back() {
+        let framebuffer = framebuffer();
+        let region = framebuffer.crop(1, 1, 2, 1);
+        assert_eq!((region.width(), region.height()), (2, 1));
+        assert_eq!(region.samples(0, 0), framebuffer.samples(1, 1));
+        assert_eq!(region.color(1, 0), framebuffer.color(2, 1));
+
+        let mut rebuilt = Framebuffer::new(3, 2);
+        rebuilt.merge(&framebuffer.crop(0, 0, 3, 1), 0, 0);
+        rebuilt.merge(&framebuffer.crop(0, 1, 1, 1), 0, 1);
+        rebuilt.merge(&region, 1, 1);
+        assert!(same_samples(&rebuilt, &framebuffer));
+    }
+
+    #[test]
+    fn samples_are_read_back_exactly() {
+        let framebuffer = framebuffer();
+        let mut bytes = Vec::new();
+        framebuffer.write_samples(&mut bytes).unwrap();
+        let read = Framebuffer::read_samples(&mut bytes.as_slice(), 3, 2).unwrap();
+        assert!(same_samples(&read, &framebuffer));
+
+        let error = Framebuffer::read_samples(&mut bytes.as_slice(), 2, 3)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let truncated = &bytes[..bytes.len() - 1];
+        let error = Framebuffer::read_samples(&mut &truncated[..], 3, 2)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn noise_is_the_relative_standard_error() {
+        let mut framebuffer = Framebuffer::new(3, 1);
+        let white = Color::new(1.0, 1.0, 1.0);
+        for color in [white, white, Color::zero(), Color::zero()] {
+            framebuffer.add_sample(0, 0, (0.0, 0.0), color);
+        }
+        // Luminances 1, 1, 0, 0 have a variance of 1/3 and a mean of 1/2
+        assert!((framebuffer.variance(0, 0) - 1.0 / 3.0).abs() < 1e-12);
+        let noise = (1.0 / 3.0 / 4.0f64).sqrt() / 0.5;
+        assert!((framebuffer.noise(0, 0) - noise).abs() < 1e-12);
+
+        // A single sample doesn't tell the noise, and equal samples have none
+        framebuffer.add_sample(1, 0, (0.0, 0.0), white);
+        assert_eq!(framebuffer.variance(1, 0), 0.0);
+        assert_eq!(framebuffer.noise(1, 0), f64::INFINITY);
+        framebuffer.add_sample(1, 0, (0.0, 0.0), white);
+        assert_eq!(framebuffer.noise(1, 0), 0.0);
+
+        // Almost black pixels are measured against a minimum luminance
+        framebuffer.add_sample(2, 0, (0.0, 0.0), Color::zero());
+        framebuffer.add_sample(2, 0, (0.0, 0.0), white * 0.002);
+        let noise = (2e-6f64 / 2.0).sqrt() / MIN_LUMINANCE;
+        assert!((framebuffer.noise(2, 0) - noise).abs() < 1e-9);
+        let average = (framebuffer.noise(0, 0) + noise) / 3.0;
+        assert!((framebuffer.average_noise() - average).abs() < 1e-9);
+    }
+}
@@ -1,25 +1,39 @@
+mod animated;
 mod hittable_list;
+mod moving_sphere;
 mod sphere;
 
 use crate::{
     material::Material,
+    random_float,
     ray::Ray,
     vec3::{Point3, Vec3},
 };
 use std::rc::Rc;
 
-pub use {hittable_list::HittableList, sphere::Sphere};
+pub use {
+    animated::Animated, hittable_list::HittableList, moving_sphere::MovingSphere, sphere::Sphere,
+};
 
 /// Structure containing the information of a ray hit against a hittable
+#[derive(Clone)]
 pub struct HitRecord {
     /// Point where the ray hitted
     pub p: Point3,
     /// Normal of the hit
     pub normal: Vec3,
+    /// Change of the point with the u surface coordinate (dp/du), used to orient normal maps
+    pub tangent: Vec3,
+    /// Change of the point with the v surface coordinate (dp/dv), used to orient normal maps
+    pub bitangent: Vec3,
     /// Materia the ray hitted
     pub material: Rc<dyn Material>,
     /// The time it took the ray to hit
     pub t: f64,
+    /// Horizontal surface coordinate of the hit, used to sample textures
+    pub u: f64,
+    /// Vertical surface coordinate of the hit, used to sample textures
+    pub v: f64,
     /// If the ray hitted from the outside or the inside of the object
     pub front_face: bool,
 }
@@ -31,30 +45,75 @@ impl HitRecord {
     ///
     /// * t - The time it took the ray to hit the object
     /// * p - Point where the ray hitted
+    /// * (u, v) - Surface coordinates of the hit
     /// * material - Material the ray hitted
     /// * outward_normal - Normal of the hit directed to the ouside of the object that was hitted
+    /// * (tangent, bitangent) - Change of the point with the u and v surface coordinates
     /// * ray - The ray that hitted the object
     pub fn create(
         t: f64,
         p: Point3,
+        (u, v): (f64, f64),
         material: &Rc<dyn Material>,
         outward_normal: &Vec3,
+        (tangent, bitangent): (Vec3, Vec3),
         ray: &Ray,
     ) -> HitRecord {
         let front_face = Vec3::dot(outward_normal, &ray.dir) < 0.0;
         let normal = if front_face {
-            outward_normal.clone()
+            *outward_normal
         } else {
-            -outward_normal.clone()
+            -*outward_normal
         };
         HitRecord {
             p,
             normal,
+            tangent,
+            bitangent,
             material: Rc::clone(material),
             t,
+            u,
+            v,
             front_face,
         }
     }
+
+    /// Returns true if the hit stops the ray according to the opacity of the material, rays go
+    /// through transparent parts and through partially opaque ones with a probability equal to
+    /// their transparency, so hittables can look for the next hit behind them
+    pub fn is_opaque(&self) -> bool {
+        let opacity = self.material.opacity(self);
+        opacity >= 1.0 || (opacity > 0.0 && random_float(0.0, 1.0) < opacity)
+    }
+
+    /// Returns the unit tangent, bitangent and outward normal that form the surface's frame
+    ///
+    /// The frame is built from the outward normal, not from `normal` which faces the ray, so it
+    /// keeps its orientation on back faces. The tangent is made perpendicular to the normal, and
+    /// if the hittable didn't give one (or it is degenerated, like at the poles of a sphere) any
+    /// perpendicular vector is used. The bitangent points to where v grows when it's known.
+    pub fn tangent_frame(&self) -> (Vec3, Vec3, Vec3) {
+        let normal = if self.front_face {
+            self.normal
+        } else {
+            -self.normal
+        };
+        let mut tangent = self.tangent - normal * Vec3::dot(&normal, &self.tangent);
+        if tangent.lenght_squared() < 1e-12 {
+            let axis = if normal.x.abs() > 0.9 {
+                Vec3::new(0.0, 1.0, 0.0)
+            } else {
+                Vec3::new(1.0, 0.0, 0.0)
+            };
+            tangent = Vec3::cross(&axis, &normal);
+        }
+        let tangent = Vec3::unit_vector(&tangent);
+        let mut bitangent = Vec3::cross(&normal, &tangent);
+        if Vec3::dot(&bitangent, &self.bitangent) < 0.0 {
+            bitangent = -bitangent;
+        }
+        (tangent, bitangent, normal)
+    }
 }
 
 /// Trait that represents a object that is hittable by a ray
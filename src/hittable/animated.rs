@@ -0,0 +1,72 @@
+use crate::{
+    animation::Track,
+    degress_to_radians,
+    hittable::{HitRecord, Hittable},
+    ray::Ray,
+    vec3::Vec3,
+};
+
+/// Wraps a hittable and moves it with keyframed transforms, evaluated at the time of each ray
+/// so objects blur while the camera's shutter is open
+pub struct Animated {
+    /// The hittable in its own space
+    object: Box<dyn Hittable>,
+    /// Translation applied after the rotation
+    translation: Track<Vec3>,
+    /// Rotation around the y axis, in degrees
+    rotation_y: Track<f64>,
+}
+
+impl Animated {
+    /// Returns the hittable without movement, add it with `with_translation` and `with_rotation_y`
+    ///
+    /// # Arguments
+    ///
+    /// * object - The hittable to animate
+    pub fn new(object: impl Hittable + 'static) -> Self {
+        Self {
+            object: Box::new(object),
+            translation: Track::constant(Vec3::zero()),
+            rotation_y: Track::constant(0.0),
+        }
+    }
+
+    /// Returns the animated hittable with the given translation track
+    pub fn with_translation(mut self, translation: Track<Vec3>) -> Self {
+        self.translation = translation;
+        self
+    }
+
+    /// Returns the animated hittable with the given rotation around the y axis track, in degrees
+    pub fn with_rotation_y(mut self, rotation_y: Track<f64>) -> Self {
+        self.rotation_y = rotation_y;
+        self
+    }
+}
+
+/// Returns the vector rotated around the y axis by the angle with the given sine and cosine
+fn rotate_y(v: &Vec3, sin: f64, cos: f64) -> Vec3 {
+    Vec3::new(cos * v.x + sin * v.z, v.y, -sin * v.x + cos * v.z)
+}
+
+impl Hittable for Animated {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let offset = self.translation.value_at(ray.time);
+        let theta = degress_to_radians(self.rotation_y.value_at(ray.time));
+        let (sin, cos) = theta.sin_cos();
+
+        // The ray is moved to the object's space, and the hit back to the world's space
+        let local = Ray {
+            origin: rotate_y(&(ray.origin - offset), -sin, cos),
+            dir: rotate_y(&ray.dir, -sin, cos),
+            ..*ray
+        };
+
+        let mut rec = self.object.hit(&local, t_min, t_max)?;
+        rec.p = rotate_y(&rec.p, sin, cos) + offset;
+        rec.normal = rotate_y(&rec.normal, sin, cos);
+        rec.tangent = rotate_y(&rec.tangent, sin, cos);
+        rec.bitangent = rotate_y(&rec.bitangent, sin, cos);
+        Some(rec)
+    }
+}
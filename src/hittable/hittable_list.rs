@@ -1,9 +1,11 @@
 use crate::{
     hittable::{HitRecord, Hittable},
     ray::Ray,
+    stats::count_intersection_test,
 };
 
 /// Struct that contains a vector of hittables
+#[derive(Default)]
 pub struct HittableList {
     objects: Vec<Box<dyn Hittable>>,
 }
@@ -28,6 +30,7 @@ impl Hittable for HittableList {
         let mut closest_so_far = t_max;
 
         for hittable in &self.objects {
+            count_intersection_test();
             if let Some(temp_rec) = hittable.hit(ray, t_min, closest_so_far) {
                 closest_so_far = temp_rec.t;
                 rec = Some(temp_rec);
@@ -0,0 +1,62 @@
+use crate::{
+    hittable::{HitRecord, Hittable, Sphere},
+    material::Material,
+    ray::Ray,
+    vec3::Point3,
+};
+
+/// Represents a sphere that moves in a straight line between two moments, blurring in renders
+/// whose camera's shutter is open during the movement
+pub struct MovingSphere {
+    /// The sphere at its initial position
+    sphere: Sphere,
+    /// Center at the initial moment
+    center0: Point3,
+    /// Center at the final moment
+    center1: Point3,
+    /// Initial moment in seconds
+    time0: f64,
+    /// Final moment in seconds
+    time1: f64,
+}
+
+impl MovingSphere {
+    /// Creates and returns a moving sphere
+    ///
+    /// # Arguments
+    ///
+    /// * (center0, time0) - Center of the sphere at the initial moment
+    /// * (center1, time1) - Center of the sphere at the final moment
+    /// * radius - Radius of the sphere
+    /// * material - Material of the sphere
+    pub fn create(
+        (center0, time0): (Point3, f64),
+        (center1, time1): (Point3, f64),
+        radius: f64,
+        material: impl Material + 'static,
+    ) -> MovingSphere {
+        MovingSphere {
+            sphere: Sphere::create(center0, radius, material),
+            center0,
+            center1,
+            time0,
+            time1,
+        }
+    }
+
+    /// Returns the center of the sphere at the given moment, it keeps moving with the same
+    /// velocity outside of the two moments, and stays at center0 if both moments are the same
+    pub fn center(&self, time: f64) -> Point3 {
+        if self.time1 == self.time0 {
+            return self.center0;
+        }
+        self.center0
+            + (self.center1 - self.center0) * ((time - self.time0) / (self.time1 - self.time0))
+    }
+}
+
+impl Hittable for MovingSphere {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.sphere.hit_at(self.center(ray.time), ray, t_min, t_max)
+    }
+}
@@ -1,3 +1,4 @@
+use crate::PI;
 use crate::{
     hittable::{HitRecord, Hittable},
     material::Material,
@@ -16,17 +17,65 @@ pub struct Sphere {
 impl Sphere {
     /// Creates and returns a sphere with the gigen center, radius and material
     pub fn create(center: Point3, radius: f64, material: impl Material + 'static) -> Sphere {
+        Sphere::with_shared_material(center, radius, Rc::new(material))
+    }
+
+    /// Creates and returns a sphere with a material that may also be used by other objects
+    pub fn with_shared_material(center: Point3, radius: f64, material: Rc<dyn Material>) -> Sphere {
         Sphere {
             center,
             radius,
-            material: Rc::new(material),
+            material,
         }
     }
-}
 
-impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let oc = &ray.origin - &self.center;
+    /// Returns the surface coordinates (u, v) of a point on the unit sphere, u being the angle
+    /// around the y axis starting at x = -1 and v the angle from y = -1 to y = 1, both between 0 and 1
+    ///
+    /// # Arguments
+    ///
+    /// * p - point on the sphere of radius one centered at the origin
+    fn get_sphere_uv(p: &Point3) -> (f64, f64) {
+        let theta = (-p.y).acos();
+        let phi = (-p.z).atan2(p.x) + PI;
+
+        (phi / (2.0 * PI), theta / PI)
+    }
+
+    /// Returns the change of a point of the sphere with the u and v surface coordinates
+    /// (dp/du, dp/dv), dp/dv is zero at the poles where it's undefined
+    ///
+    /// # Arguments
+    ///
+    /// * n - outward normal of the point
+    fn derivatives(&self, n: &Vec3) -> (Vec3, Vec3) {
+        let dpdu = Vec3::new(n.z, 0.0, -n.x) * (2.0 * PI * self.radius);
+        let sin_theta = (n.x * n.x + n.z * n.z).sqrt();
+        let dpdv = if sin_theta < 1e-9 {
+            Vec3::zero()
+        } else {
+            Vec3::new(-n.x * n.y / sin_theta, sin_theta, -n.z * n.y / sin_theta)
+                * (PI * self.radius)
+        };
+        (dpdu, dpdv)
+    }
+
+    /// Returns the hit of the ray against the sphere as if it was centered at the given point
+    ///
+    /// # Arguments
+    ///
+    /// * center - The center of the sphere
+    /// * ray - The ray that was casted
+    /// * t_min - minimum time
+    /// * t_max - maximum time
+    pub(crate) fn hit_at(
+        &self,
+        center: Point3,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Option<HitRecord> {
+        let oc = ray.origin - center;
         let a = ray.dir.lenght_squared();
         let half_b = Vec3::dot(&oc, &ray.dir);
         let c = oc.lenght_squared() - self.radius * self.radius;
@@ -38,19 +87,99 @@ impl Hittable for Sphere {
 
         let sqrtd = discriminant.sqrt();
 
-        let mut root = (-half_b - sqrtd) / a;
-        if root < t_min || root > t_max {
-            root = (-half_b + sqrtd) / a;
+        // The nearest root is tried first, the farthest is used when the nearest one is out of
+        // range or is in a transparent part of the material
+        for root in [(-half_b - sqrtd) / a, (-half_b + sqrtd) / a] {
             if root < t_min || root > t_max {
-                return None;
+                continue;
             }
-        };
 
-        let p = ray.at(root);
-        let outward_normal = (&p - &self.center) / self.radius;
+            let p = ray.at(root);
+            let outward_normal = (p - center) / self.radius;
 
-        let rec = HitRecord::create(root, p, &self.material, &outward_normal, ray);
+            let uv = Sphere::get_sphere_uv(&outward_normal);
+            let derivatives = self.derivatives(&outward_normal);
+
+            let rec = HitRecord::create(
+                root,
+                p,
+                uv,
+                &self.material,
+                &outward_normal,
+                derivatives,
+                ray,
+            );
+
+            if rec.is_opaque() {
+                return Some(rec);
+            }
+        }
+
+        None
+    }
+}
+
+impl Hittable for Sphere {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.hit_at(self.center, ray, t_min, t_max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color::Color, material::Lambertian};
+
+    /// Checks that the surface coordinates are the expected ones
+    fn assert_uv((u, v): (f64, f64), expected: (f64, f64)) {
+        assert!(
+            (u - expected.0).abs() < 1e-9,
+            "u is {} instead of {}",
+            u,
+            expected.0
+        );
+        assert!(
+            (v - expected.1).abs() < 1e-9,
+            "v is {} instead of {}",
+            v,
+            expected.1
+        );
+    }
+
+    #[test]
+    fn surface_coordinates_wrap_around_the_y_axis() {
+        assert_uv(
+            Sphere::get_sphere_uv(&Point3::new(-1.0, 0.0, 0.0)),
+            (0.0, 0.5),
+        );
+        assert_uv(
+            Sphere::get_sphere_uv(&Point3::new(0.0, 0.0, 1.0)),
+            (0.25, 0.5),
+        );
+        assert_uv(
+            Sphere::get_sphere_uv(&Point3::new(1.0, 0.0, 0.0)),
+            (0.5, 0.5),
+        );
+        assert_uv(
+            Sphere::get_sphere_uv(&Point3::new(0.0, 0.0, -1.0)),
+            (0.75, 0.5),
+        );
+        assert_eq!(Sphere::get_sphere_uv(&Point3::new(0.0, -1.0, 0.0)).1, 0.0);
+        assert_eq!(Sphere::get_sphere_uv(&Point3::new(0.0, 1.0, 0.0)).1, 1.0);
+    }
 
-        Some(rec)
+    #[test]
+    fn hits_have_the_surface_coordinates_of_the_point() {
+        let sphere = Sphere::create(
+            Point3::new(0.0, 0.0, -5.0),
+            2.0,
+            Lambertian::new(Color::new(0.5, 0.5, 0.5)),
+        );
+        let ray = Ray::create(Point3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        let rec = sphere.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(rec.t, 3.0);
+        assert_eq!(rec.p, Point3::new(0.0, 0.0, -3.0));
+        assert!(rec.front_face);
+        assert_uv((rec.u, rec.v), (0.25, 0.5));
     }
 }
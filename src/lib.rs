@@ -3,25 +3,50 @@
 //! A ray tracer written in rust following the [_Ray Tracing in One Weekend_](https://raytracing.github.io/books/RayTracingInOneWeekend.html).
 //! This crate also took inspiration from the ray tracer develop by [_gkmngrgn_](https://github.com/gkmngrgn)
 
+pub mod animation;
 pub mod camera;
 pub mod color;
+pub mod distributed;
+pub mod filter;
+pub mod framebuffer;
 pub mod hittable;
 pub mod material;
+pub mod png;
 pub mod ray;
 pub mod renderer;
+pub mod scene;
+pub mod service;
+pub mod spectrum;
+pub mod stats;
+pub mod texture;
 pub mod vec3;
 
-use rand::prelude::*;
-pub use std::f64::{consts::PI, MAX};
+use rand::{prelude::*, rngs::StdRng};
+use std::cell::RefCell;
+pub use std::f64::consts::PI;
+
+/// Largest finite f64 value, used as the upper bound of the ray hit time
+pub const MAX: f64 = f64::MAX;
 
 /// Converts a number in degrees to radians
 pub fn degress_to_radians(degrees: f64) -> f64 {
     degrees * PI / 180.0
 }
 
+thread_local! {
+    /// Generator of the random numbers of the current thread, seeded from the system's entropy
+    /// until `seed_random` is called
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+/// Reseeds the random numbers of the current thread, the same seed repeats the same numbers
+pub fn seed_random(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
 /// Returns a random float between the min amnd max given
 pub fn random_float(min: f64, max: f64) -> f64 {
-    min + (max - min) * thread_rng().gen::<f64>()
+    min + (max - min) * RNG.with(|rng| rng.borrow_mut().gen::<f64>())
 }
 
 /// Return x if it's in the range between min and max, if it's over returns max and if it's under returns min
@@ -1,8 +1,20 @@
+mod alpha_mask;
 mod dielectric;
 mod lambertian;
 mod metal;
+mod mix;
+mod normal_map;
+mod principled;
 
-pub use {dielectric::Dielectric, lambertian::Lambertian, metal::Metal};
+pub use {
+    alpha_mask::AlphaMask,
+    dielectric::{Dielectric, Ior},
+    lambertian::Lambertian,
+    metal::Metal,
+    mix::Mix,
+    normal_map::{BumpMap, NormalMap},
+    principled::Principled,
+};
 
 use crate::{color::Color, hittable::HitRecord, ray::Ray};
 
@@ -15,4 +27,14 @@ pub trait Material {
     /// * r_in - The incoming ray that will be scattered
     /// * rec - HitRecord containing the informations about the hit
     fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)>;
+
+    /// Returns how opaque the material is at the hit, between 0 (rays go through it as if
+    /// nothing was hit) and 1 (the default, rays always hit it)
+    ///
+    /// # Arguments
+    ///
+    /// * rec - HitRecord containing the informations about the hit
+    fn opacity(&self, _rec: &HitRecord) -> f64 {
+        1.0
+    }
 }
@@ -0,0 +1,93 @@
+use crate::{
+    clamp, color::Color, hittable::HitRecord, material::Material, ray::Ray, texture::Texture,
+};
+use std::rc::Rc;
+
+/// Wraps a material and cuts holes in it with an opacity texture, used for leaves, fences and decals
+pub struct AlphaMask {
+    /// Material of the opaque parts
+    material: Rc<dyn Material>,
+    /// Scalar texture with the opacity, 0 is transparent and 1 is opaque
+    mask: Rc<dyn Texture>,
+}
+
+impl AlphaMask {
+    /// Returns the material masked by the opacity texture
+    ///
+    /// # Arguments
+    ///
+    /// * material - The material of the opaque parts
+    /// * mask - Scalar opacity texture, read with `ImageTexture::open_linear`
+    pub fn new(material: impl Material + 'static, mask: impl Texture + 'static) -> Self {
+        Self {
+            material: Rc::new(material),
+            mask: Rc::new(mask),
+        }
+    }
+}
+
+impl Material for AlphaMask {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+        self.material.scatter(r_in, rec)
+    }
+
+    fn opacity(&self, rec: &HitRecord) -> f64 {
+        let mask = clamp(self.mask.value(rec.u, rec.v, &rec.p).x, 0.0, 1.0);
+        mask * self.material.opacity(rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hittable::{Hittable, Sphere},
+        material::Lambertian,
+        vec3::{Point3, Vec3},
+    };
+
+    /// Opacity texture that is opaque only behind the plane z = -5
+    struct Behind;
+
+    impl Texture for Behind {
+        fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+            let opacity = if p.z < -5.0 { 1.0 } else { 0.0 };
+            Color::new(opacity, opacity, opacity)
+        }
+    }
+
+    /// Returns the material of the opaque parts of the masks
+    fn gray() -> Lambertian {
+        Lambertian::new(Color::new(0.5, 0.5, 0.5))
+    }
+
+    /// Returns the hit of a ray going along -z against a sphere of radius 2 centered at z = -5
+    fn hit(material: impl Material + 'static) -> Option<HitRecord> {
+        let sphere = Sphere::create(Point3::new(0.0, 0.0, -5.0), 2.0, material);
+        let ray = Ray::create(Point3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        sphere.hit(&ray, 0.001, f64::INFINITY)
+    }
+
+    #[test]
+    fn opacity_is_the_clamped_mask_times_the_inner_opacity() {
+        let rec = hit(gray()).unwrap();
+        assert_eq!(AlphaMask::new(gray(), 0.25).opacity(&rec), 0.25);
+        assert_eq!(AlphaMask::new(gray(), 2.0).opacity(&rec), 1.0);
+        assert_eq!(AlphaMask::new(gray(), -1.0).opacity(&rec), 0.0);
+        let nested = AlphaMask::new(AlphaMask::new(gray(), 0.5), 0.5);
+        assert_eq!(nested.opacity(&rec), 0.25);
+    }
+
+    #[test]
+    fn rays_go_through_the_transparent_parts() {
+        assert!(hit(AlphaMask::new(gray(), 0.0)).is_none());
+
+        let rec = hit(AlphaMask::new(gray(), 1.0)).unwrap();
+        assert_eq!(rec.t, 3.0);
+
+        // The near side is transparent, so the ray hits the inside of the far side
+        let rec = hit(AlphaMask::new(gray(), Behind)).unwrap();
+        assert_eq!(rec.t, 7.0);
+        assert!(!rec.front_face);
+    }
+}
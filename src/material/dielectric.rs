@@ -1,11 +1,60 @@
-use crate::{color::Color, hittable::HitRecord, material::Material, ray::Ray, vec3::Vec3};
-use rand::prelude::*;
+use crate::{
+    color::Color, hittable::HitRecord, material::Material, random_float, ray::Ray, vec3::Vec3,
+};
+
+/// Represents how the index of refraction of a material changes with the wavelength
+#[derive(Clone, Copy)]
+pub enum Ior {
+    /// The same index of refraction for every wavelength
+    Constant(f64),
+    /// Cauchy's equation n = a + b / λ², with λ in micrometers
+    Cauchy { a: f64, b: f64 },
+    /// Sellmeier's equation n² = 1 + Σ b λ² / (λ² - c), with λ in micrometers
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Ior {
+    /// Borosilicate crown glass (Schott N-BK7), used in most lenses and prisms
+    pub const BK7: Ior = Ior::Sellmeier {
+        b: [1.039_612_12, 0.231_792_344, 1.010_469_45],
+        c: [0.006_000_698_67, 0.020_017_914_4, 103.560_653],
+    };
+    /// Diamond, with its strong dispersion
+    pub const DIAMOND: Ior = Ior::Sellmeier {
+        b: [4.3356, 0.3306, 0.0],
+        c: [0.106 * 0.106, 0.175 * 0.175, 0.0],
+    };
+
+    /// Wavelength used when a ray doesn't carry one, the yellow helium d line in nanometers
+    const REFERENCE_WAVELENGTH: f64 = 587.56;
+
+    /// Returns the index of refraction at the given wavelength
+    ///
+    /// # Arguments
+    ///
+    /// * wavelength - The wavelength in nanometers, if None the reference wavelength of 587.56nm is used
+    pub fn at(&self, wavelength: Option<f64>) -> f64 {
+        let lambda = wavelength.unwrap_or(Ior::REFERENCE_WAVELENGTH) / 1000.0;
+        let lambda2 = lambda * lambda;
+        match self {
+            Ior::Constant(ir) => *ir,
+            Ior::Cauchy { a, b } => a + b / lambda2,
+            Ior::Sellmeier { b, c } => (1.0
+                + (0..3)
+                    .map(|i| b[i] * lambda2 / (lambda2 - c[i]))
+                    .sum::<f64>())
+            .sqrt(),
+        }
+    }
+}
 
 /// Represents a material that lets light go through it
 #[derive(Clone, Copy)]
 pub struct Dielectric {
     /// The index of refraction of the material
-    ir: f64,
+    ir: Ior,
+    /// How much of each color channel is absorbed per unit of distance traveled inside the material
+    absorption: Color,
 }
 
 impl Dielectric {
@@ -15,7 +64,48 @@ impl Dielectric {
     ///
     /// * ir - index of refraction to give to the material
     pub fn new(ir: f64) -> Self {
-        Self { ir }
+        Self::dispersive(Ior::Constant(ir), Color::zero())
+    }
+
+    /// Returns a Dielectric material whose index of refraction depends on the wavelength,
+    /// splitting white light into its colors when rendered in spectral mode
+    ///
+    /// # Arguments
+    ///
+    /// * ir - index of refraction curve to give to the material
+    /// * absorption - absorption coefficient of each color channel per unit of distance
+    pub fn dispersive(ir: Ior, absorption: Color) -> Self {
+        Self { ir, absorption }
+    }
+
+    /// Returns a Dielectric material that absorbs light inside it following the Beer-Lambert law
+    ///
+    /// # Arguments
+    ///
+    /// * ir - index of refraction to give to the material
+    /// * absorption - absorption coefficient of each color channel per unit of distance
+    pub fn with_absorption(ir: f64, absorption: Color) -> Self {
+        Self::dispersive(Ior::Constant(ir), absorption)
+    }
+
+    /// Returns a Dielectric material tinted with the color light has after traveling a given
+    /// distance inside it, so thicker parts look darker and thinner parts look clearer
+    ///
+    /// # Arguments
+    ///
+    /// * ir - index of refraction to give to the material
+    /// * transmission_color - color of the light after traveling the given distance, each channel between 0 and 1
+    /// * distance - distance at which the light has the transmission color
+    pub fn tinted(ir: f64, transmission_color: Color, distance: f64) -> Self {
+        let absorption = |c: f64| -c.max(1e-6).ln() / distance;
+        Self::with_absorption(
+            ir,
+            Color::new(
+                absorption(transmission_color.x),
+                absorption(transmission_color.y),
+                absorption(transmission_color.z),
+            ),
+        )
     }
 
     /// Returns the reflectance of the material when hit with a ray in a certain angle
@@ -24,7 +114,7 @@ impl Dielectric {
     ///
     /// * cosine - The cosine of the angle between the ray that hitted the material and the normal
     /// * ref_idx - The ratio between the materials index of refraction and the outside index of refraction
-    fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
+    pub(crate) fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
         let mut r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
         r0 *= r0;
         r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
@@ -33,12 +123,19 @@ impl Dielectric {
 
 impl Material for Dielectric {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
-        let attenuation = Color::new(1.0, 1.0, 1.0);
-        let refraction_ratio = if rec.front_face {
-            1.0 / self.ir
+        // A ray that hits the inside of the object traveled through the material, so it was absorbed
+        let attenuation = if rec.front_face {
+            Color::new(1.0, 1.0, 1.0)
         } else {
-            self.ir
+            let distance = rec.t * r_in.dir.length();
+            Color::new(
+                (-self.absorption.x * distance).exp(),
+                (-self.absorption.y * distance).exp(),
+                (-self.absorption.z * distance).exp(),
+            )
         };
+        let ir = self.ir.at(r_in.wavelength);
+        let refraction_ratio = if rec.front_face { 1.0 / ir } else { ir };
 
         let unit_direction = Vec3::unit_vector(&r_in.dir);
         let cos_theta = Vec3::dot(&(-unit_direction), &rec.normal).min(1.0);
@@ -46,30 +143,100 @@ impl Material for Dielectric {
 
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
 
-        let direction;
-        if cannot_refract
-            || Dielectric::reflectance(cos_theta, refraction_ratio) > thread_rng().gen::<f64>()
+        let direction = if cannot_refract
+            || Dielectric::reflectance(cos_theta, refraction_ratio) > random_float(0.0, 1.0)
         {
-            direction = Vec3::reflect(&unit_direction, &rec.normal);
+            Vec3::reflect(&unit_direction, &rec.normal)
         } else {
-            direction = refract(&unit_direction, &rec.normal, refraction_ratio);
-        }
+            Vec3::refract(&unit_direction, &rec.normal, refraction_ratio)
+        };
 
-        let scattered = Ray::create(rec.p.clone(), direction);
+        let scattered = Ray::create(rec.p, direction);
         Some((scattered, attenuation))
     }
 }
 
-/// Returns the refractod vector from the incidence of the incoming vector agains a material
-///
-/// # Arguments
-///
-/// * uv - The vector that represents the direction of the incoming ray
-/// * n - The normal of the plane hitted
-/// * etai_over_etat - Ratio between the refraction index of the material the ray was traveling through and the material of the hitted object
-fn refract(uv: &Vec3, n: &Vec3, etai_over_etat: f64) -> Vec3 {
-    let cos_theta = Vec3::dot(&(-uv), n).min(1.0);
-    let r_out_perp: Vec3 = (uv + &(n * cos_theta)) * etai_over_etat;
-    let r_out_parallel: Vec3 = n * -(1.0 - r_out_perp.lenght_squared()).abs().sqrt();
-    r_out_parallel + r_out_perp
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::Point3;
+    use std::rc::Rc;
+
+    /// Returns the hit with the material, at the given distance, of a ray going down through
+    /// a surface whose outward normal is +y if it's entering the object, or -y if it's leaving
+    fn hit(material: Dielectric, distance: f64, entering: bool) -> (Ray, HitRecord) {
+        let material: Rc<dyn Material> = Rc::new(material);
+        let ray = Ray::create(Point3::new(0.0, distance, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let outward_normal = Vec3::new(0.0, if entering { 1.0 } else { -1.0 }, 0.0);
+        let frame = (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let rec = HitRecord::create(
+            distance,
+            Point3::zero(),
+            (0.0, 0.0),
+            &material,
+            &outward_normal,
+            frame,
+            &ray,
+        );
+        (ray, rec)
+    }
+
+    /// Checks that the colors are the same except for rounding
+    fn assert_close(actual: Color, expected: Color) {
+        assert!(
+            (actual - expected).length() < 1e-9,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn index_of_refraction_depends_on_the_wavelength() {
+        assert_eq!(Ior::Constant(1.5).at(Some(400.0)), 1.5);
+        let cauchy = Ior::Cauchy { a: 1.5, b: 0.01 };
+        assert!((cauchy.at(Some(500.0)) - 1.54).abs() < 1e-12);
+
+        // BK7 is specified at the reference wavelength, and bends blue more than red
+        assert!((Ior::BK7.at(None) - 1.5168).abs() < 1e-4);
+        assert!(Ior::BK7.at(Some(450.0)) > Ior::BK7.at(Some(650.0)));
+        assert!((Ior::DIAMOND.at(None) - 2.417).abs() < 1e-2);
+    }
+
+    #[test]
+    fn reflectance_at_normal_incidence() {
+        assert!((Dielectric::reflectance(1.0, 1.5) - 0.04).abs() < 1e-12);
+        assert_eq!(Dielectric::reflectance(0.0, 1.5), 1.0);
+    }
+
+    #[test]
+    fn light_is_absorbed_only_inside() {
+        let absorption = Color::new(0.0, 0.5, 2.0);
+        let material = Dielectric::with_absorption(1.5, absorption);
+
+        let (ray, rec) = hit(material, 2.0, true);
+        let (_, attenuation) = material.scatter(&ray, &rec).unwrap();
+        assert_eq!(attenuation, Color::new(1.0, 1.0, 1.0));
+
+        let (ray, rec) = hit(material, 2.0, false);
+        let (_, attenuation) = material.scatter(&ray, &rec).unwrap();
+        assert_close(
+            attenuation,
+            Color::new(1.0, (-1.0f64).exp(), (-4.0f64).exp()),
+        );
+    }
+
+    #[test]
+    fn tinted_glass_has_the_color_at_the_distance() {
+        let color = Color::new(0.9, 0.5, 0.1);
+        let material = Dielectric::tinted(1.5, color, 3.0);
+        let (ray, rec) = hit(material, 3.0, false);
+        let (_, attenuation) = material.scatter(&ray, &rec).unwrap();
+        assert_close(attenuation, color);
+
+        // Twice as far the light goes through the same thickness twice
+        let (ray, rec) = hit(material, 6.0, false);
+        let (_, attenuation) = material.scatter(&ray, &rec).unwrap();
+        assert_close(attenuation, color * color);
+    }
 }
@@ -15,13 +15,13 @@ impl Lambertian {
 
 impl Material for Lambertian {
     fn scatter(&self, _r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
-        let mut scatter_direction = &rec.normal + &Vec3::random_unit_vec();
+        let mut scatter_direction = rec.normal + Vec3::random_unit_vec();
 
         if scatter_direction.near_zero() {
-            scatter_direction = rec.normal.clone();
+            scatter_direction = rec.normal;
         }
 
-        let scattered = Ray::create(rec.p.clone(), scatter_direction);
+        let scattered = Ray::create(rec.p, scatter_direction);
         Some((scattered, self.albedo))
     }
 }
@@ -22,10 +22,7 @@ impl Metal {
 impl Material for Metal {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
         let reflected = Vec3::reflect(&r_in.dir, &rec.normal);
-        let scattered = Ray::create(
-            rec.p.clone(),
-            reflected + Vec3::random_in_unit_sphere() * self.fuzz,
-        );
+        let scattered = Ray::create(rec.p, reflected + Vec3::random_in_unit_sphere() * self.fuzz);
 
         if Vec3::dot(&scattered.dir, &rec.normal) > 0.0 {
             Some((scattered, self.albedo))
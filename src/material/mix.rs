@@ -0,0 +1,138 @@
+use crate::{
+    clamp, color::Color, hittable::HitRecord, material::Material, random_float, ray::Ray,
+    texture::Texture,
+};
+use std::rc::Rc;
+
+/// Represents a blend of two materials, each scatter picks one of them at random with a
+/// probability given by the mask, so on average the result is the weighted mix of both
+pub struct Mix {
+    /// Material used where the mask is 0
+    first: Rc<dyn Material>,
+    /// Material used where the mask is 1
+    second: Rc<dyn Material>,
+    /// Scalar texture with the weight of the second material
+    mask: Rc<dyn Texture>,
+}
+
+impl Mix {
+    /// Returns the blend of the two materials
+    ///
+    /// # Arguments
+    ///
+    /// * first - The material used where the mask is 0
+    /// * second - The material used where the mask is 1
+    /// * mask - A constant weight or a scalar texture with the weight of the second material
+    pub fn new(
+        first: impl Material + 'static,
+        second: impl Material + 'static,
+        mask: impl Texture + 'static,
+    ) -> Self {
+        Self::shared(Rc::new(first), Rc::new(second), mask)
+    }
+
+    /// Returns the blend of two materials that may also be used by other objects
+    ///
+    /// # Arguments
+    ///
+    /// * first - The material used where the mask is 0
+    /// * second - The material used where the mask is 1
+    /// * mask - A constant weight or a scalar texture with the weight of the second material
+    pub fn shared(
+        first: Rc<dyn Material>,
+        second: Rc<dyn Material>,
+        mask: impl Texture + 'static,
+    ) -> Self {
+        Self {
+            first,
+            second,
+            mask: Rc::new(mask),
+        }
+    }
+
+    /// Returns the weight of the second material at the hit
+    fn weight(&self, rec: &HitRecord) -> f64 {
+        clamp(self.mask.value(rec.u, rec.v, &rec.p).x, 0.0, 1.0)
+    }
+}
+
+impl Material for Mix {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+        if random_float(0.0, 1.0) < self.weight(rec) {
+            self.second.scatter(r_in, rec)
+        } else {
+            self.first.scatter(r_in, rec)
+        }
+    }
+
+    fn opacity(&self, rec: &HitRecord) -> f64 {
+        let weight = self.weight(rec);
+        self.first.opacity(rec) * (1.0 - weight) + self.second.opacity(rec) * weight
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        material::AlphaMask,
+        seed_random,
+        vec3::{Point3, Vec3},
+    };
+
+    /// Scatters every ray with its own attenuation, to tell which material was picked
+    struct Tagged(f64);
+
+    impl Material for Tagged {
+        fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+            Some((
+                Ray::create(rec.p, r_in.dir),
+                Color::new(self.0, self.0, self.0),
+            ))
+        }
+    }
+
+    /// Returns a hit with the material
+    fn hit(material: Mix) -> (Ray, HitRecord) {
+        let material: Rc<dyn Material> = Rc::new(material);
+        let ray = Ray::create(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let rec = HitRecord::create(
+            1.0,
+            Point3::zero(),
+            (0.5, 0.5),
+            &material,
+            &Vec3::new(0.0, 1.0, 0.0),
+            (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0)),
+            &ray,
+        );
+        (ray, rec)
+    }
+
+    /// Returns how many of the scatters picked the second material
+    fn second_picks(mask: f64, scatters: usize) -> usize {
+        let (ray, rec) = hit(Mix::new(Tagged(0.0), Tagged(1.0), mask));
+        (0..scatters)
+            .filter(|_| rec.material.scatter(&ray, &rec).unwrap().1.x == 1.0)
+            .count()
+    }
+
+    #[test]
+    fn the_mask_is_the_probability_of_the_second_material() {
+        seed_random(3);
+        assert_eq!(second_picks(0.0, 1000), 0);
+        assert_eq!(second_picks(1.0, 1000), 1000);
+        assert_eq!(second_picks(-1.0, 1000), 0);
+        let picks = second_picks(0.25, 10000);
+        assert!((2300..2700).contains(&picks), "{}", picks);
+    }
+
+    #[test]
+    fn opacity_is_the_weighted_mix() {
+        let (_, rec) = hit(Mix::new(Tagged(0.0), Tagged(1.0), 0.0));
+        let mix = Mix::new(AlphaMask::new(Tagged(0.0), 0.0), Tagged(1.0), 0.25);
+        assert_eq!(mix.opacity(&rec), 0.25);
+        let shared: Rc<dyn Material> = Rc::new(AlphaMask::new(Tagged(0.0), 0.5));
+        let mix = Mix::shared(shared.clone(), shared, 0.75);
+        assert_eq!(mix.opacity(&rec), 0.5);
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{
+    color::Color, hittable::HitRecord, material::Material, ray::Ray, texture::Texture, vec3::Vec3,
+};
+use std::rc::Rc;
+
+/// Step in surface coordinates used to measure the slope of a height map
+const BUMP_DELTA: f64 = 1.0 / 1024.0;
+
+/// Wraps a material and bends the normal of the hit with a tangent space normal map
+pub struct NormalMap {
+    /// Material that scatters the ray with the perturbed normal
+    material: Rc<dyn Material>,
+    /// Texture whose colors (0 to 1) encode the normal in tangent space (-1 to 1)
+    map: Rc<dyn Texture>,
+    /// How much the normal is bent, 0 keeps the geometric normal and 1 uses the map's
+    strength: f64,
+}
+
+impl NormalMap {
+    /// Returns the material with its normals perturbed by the normal map
+    ///
+    /// # Arguments
+    ///
+    /// * material - The material to wrap
+    /// * map - Tangent space normal map, read with `ImageTexture::open_linear`
+    /// * strength - How much the normal is bent, between 0 and 1
+    pub fn new(
+        material: impl Material + 'static,
+        map: impl Texture + 'static,
+        strength: f64,
+    ) -> Self {
+        Self {
+            material: Rc::new(material),
+            map: Rc::new(map),
+            strength,
+        }
+    }
+}
+
+impl Material for NormalMap {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+        let (tangent, bitangent, normal) = rec.tangent_frame();
+        let c = self.map.value(rec.u, rec.v, &rec.p);
+        let mapped = tangent * (2.0 * c.x - 1.0)
+            + bitangent * (2.0 * c.y - 1.0)
+            + normal * (2.0 * c.z - 1.0);
+        let bent = Vec3::unit_vector(&(normal * (1.0 - self.strength) + mapped * self.strength));
+
+        let mut rec = rec.clone();
+        rec.normal = if rec.front_face { bent } else { -bent };
+        self.material.scatter(r_in, &rec)
+    }
+
+    fn opacity(&self, rec: &HitRecord) -> f64 {
+        self.material.opacity(rec)
+    }
+}
+
+/// Wraps a material and bends the normal of the hit following the slope of a height map
+pub struct BumpMap {
+    /// Material that scatters the ray with the perturbed normal
+    material: Rc<dyn Material>,
+    /// Scalar texture with the height of the surface
+    height: Rc<dyn Texture>,
+    /// Height of the bumps in units of distance for each unit of the height map, negative
+    /// values invert the bumps
+    scale: f64,
+}
+
+impl BumpMap {
+    /// Returns the material with its normals perturbed by the height map
+    ///
+    /// # Arguments
+    ///
+    /// * material - The material to wrap
+    /// * height - Scalar height texture
+    /// * scale - Height of the bumps in units of distance for each unit of the height map
+    pub fn new(
+        material: impl Material + 'static,
+        height: impl Texture + 'static,
+        scale: f64,
+    ) -> Self {
+        Self {
+            material: Rc::new(material),
+            height: Rc::new(height),
+            scale,
+        }
+    }
+}
+
+impl Material for BumpMap {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+        let (tangent, bitangent, normal) = rec.tangent_frame();
+        let height = |u: f64, v: f64| self.height.value(u, v, &rec.p).x;
+
+        let h = height(rec.u, rec.v);
+        let dhdu = (height(rec.u + BUMP_DELTA, rec.v) - h) / BUMP_DELTA;
+        let dhdv = (height(rec.u, rec.v + BUMP_DELTA) - h) / BUMP_DELTA;
+        // The slopes are per unit of u and v, dividing by the length of dp/du and dp/dv makes
+        // them per unit of distance so the bumps don't depend on the scale of the coordinates
+        let length = |v: &Vec3| match v.length() {
+            l if l > 1e-12 => l,
+            _ => 1.0,
+        };
+        let gradient =
+            tangent * (dhdu / length(&rec.tangent)) + bitangent * (dhdv / length(&rec.bitangent));
+        let bent = Vec3::unit_vector(&(normal - gradient * self.scale));
+
+        let mut rec = rec.clone();
+        rec.normal = if rec.front_face { bent } else { -bent };
+        self.material.scatter(r_in, &rec)
+    }
+
+    fn opacity(&self, rec: &HitRecord) -> f64 {
+        self.material.opacity(rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::Point3;
+
+    /// Scatters the ray in the direction of the normal it's given, to see how it was bent
+    struct Probe;
+
+    impl Material for Probe {
+        fn scatter(&self, _r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+            Some((Ray::create(rec.p, rec.normal), Color::new(1.0, 1.0, 1.0)))
+        }
+    }
+
+    /// Height map that grows with the u surface coordinate
+    struct Ramp;
+
+    impl Texture for Ramp {
+        fn value(&self, u: f64, _v: f64, _p: &Point3) -> Color {
+            Color::new(u, u, u)
+        }
+    }
+
+    /// Returns the normal the material scatters with at a hit on a surface whose outward normal
+    /// is +y, tangent +x and bitangent +z, from above or from below
+    fn bent_normal(material: impl Material + 'static, from_above: bool) -> Vec3 {
+        let material: Rc<dyn Material> = Rc::new(material);
+        let direction = Vec3::new(0.0, if from_above { -1.0 } else { 1.0 }, 0.0);
+        let ray = Ray::create(-direction, direction);
+        let rec = HitRecord::create(
+            1.0,
+            Point3::zero(),
+            (0.5, 0.5),
+            &material,
+            &Vec3::new(0.0, 1.0, 0.0),
+            (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0)),
+            &ray,
+        );
+        material.scatter(&ray, &rec).unwrap().0.dir
+    }
+
+    /// Checks that the vectors are the same except for rounding
+    fn assert_close(actual: Vec3, expected: Vec3) {
+        assert!(
+            (actual - expected).length() < 1e-6,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn flat_normal_maps_keep_the_normal() {
+        let flat = NormalMap::new(Probe, Color::new(0.5, 0.5, 1.0), 1.0);
+        assert_close(bent_normal(flat, true), Vec3::new(0.0, 1.0, 0.0));
+        let weak = NormalMap::new(Probe, Color::new(1.0, 0.5, 0.5), 0.0);
+        assert_close(bent_normal(weak, true), Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn normal_maps_are_read_in_tangent_space() {
+        let along_u = || NormalMap::new(Probe, Color::new(1.0, 0.5, 0.5), 1.0);
+        assert_close(bent_normal(along_u(), true), Vec3::new(1.0, 0.0, 0.0));
+        let along_v = NormalMap::new(Probe, Color::new(0.5, 1.0, 0.5), 1.0);
+        assert_close(bent_normal(along_v, true), Vec3::new(0.0, 0.0, 1.0));
+        // The frame keeps its orientation on back faces, and the normal still faces the ray
+        assert_close(bent_normal(along_u(), false), Vec3::new(-1.0, 0.0, 0.0));
+
+        let half = NormalMap::new(Probe, Color::new(1.0, 0.5, 0.5), 0.5);
+        let diagonal = Vec3::unit_vector(&Vec3::new(1.0, 1.0, 0.0));
+        assert_close(bent_normal(half, true), diagonal);
+    }
+
+    #[test]
+    fn bump_maps_follow_the_slope() {
+        let flat = BumpMap::new(Probe, 0.3, 1.0);
+        assert_close(bent_normal(flat, true), Vec3::new(0.0, 1.0, 0.0));
+
+        // The height grows one unit per unit of distance along the tangent, so the normal
+        // leans 45 degrees away from it, or towards it when the bumps are inverted
+        let ramp = BumpMap::new(Probe, Ramp, 1.0);
+        let away = Vec3::unit_vector(&Vec3::new(-1.0, 1.0, 0.0));
+        assert_close(bent_normal(ramp, true), away);
+        let inverted = BumpMap::new(Probe, Ramp, -1.0);
+        let towards = Vec3::unit_vector(&Vec3::new(1.0, 1.0, 0.0));
+        assert_close(bent_normal(inverted, true), towards);
+    }
+}
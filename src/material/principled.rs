@@ -0,0 +1,298 @@
+use crate::{
+    clamp,
+    color::Color,
+    hittable::HitRecord,
+    material::{Dielectric, Material},
+    random_float,
+    ray::Ray,
+    texture::Texture,
+    vec3::Vec3,
+};
+use std::rc::Rc;
+
+/// Represents a layered "uber" material in the style of the Disney principled BSDF
+///
+/// Each scatter picks one lobe at random, from the top layer to the bottom one: clearcoat,
+/// metal, glass (transmission), dielectric specular and finally diffuse with sheen.
+/// Every parameter is a texture, so maps from imported assets can be used directly.
+pub struct Principled {
+    /// Color of the diffuse, metal and transmission lobes
+    base_color: Rc<dyn Texture>,
+    /// Blend between dielectric (0) and metal (1)
+    metallic: Rc<dyn Texture>,
+    /// Glossiness of the reflections and refractions, 0 is a mirror and 1 is very rough
+    roughness: Rc<dyn Texture>,
+    /// Amount of dielectric specular reflection, 0.5 is a common 4% reflectance at normal incidence
+    specular: Rc<dyn Texture>,
+    /// Blend between opaque (0) and glass like (1) dielectric
+    transmission: Rc<dyn Texture>,
+    /// Strength of a white, glossy coat on top of the other layers
+    clearcoat: Rc<dyn Texture>,
+    /// Strength of the soft white reflection at grazing angles, used for cloth
+    sheen: Rc<dyn Texture>,
+    /// Index of refraction used by the transmission lobe
+    ir: f64,
+}
+
+impl Principled {
+    /// Returns a principled material with the given base color, a roughness of 0.5, a specular
+    /// of 0.5, an index of refraction of 1.5 and every other parameter at zero
+    ///
+    /// # Arguments
+    ///
+    /// * base_color - texture or color of the material
+    pub fn new(base_color: impl Texture + 'static) -> Self {
+        Self {
+            base_color: Rc::new(base_color),
+            metallic: Rc::new(0.0),
+            roughness: Rc::new(0.5),
+            specular: Rc::new(0.5),
+            transmission: Rc::new(0.0),
+            clearcoat: Rc::new(0.0),
+            sheen: Rc::new(0.0),
+            ir: 1.5,
+        }
+    }
+
+    /// Returns the material with the given metallic texture (0 dielectric, 1 metal)
+    pub fn with_metallic(mut self, metallic: impl Texture + 'static) -> Self {
+        self.metallic = Rc::new(metallic);
+        self
+    }
+
+    /// Returns the material with the given roughness texture (0 mirror, 1 rough)
+    pub fn with_roughness(mut self, roughness: impl Texture + 'static) -> Self {
+        self.roughness = Rc::new(roughness);
+        self
+    }
+
+    /// Returns the material with the given specular texture
+    pub fn with_specular(mut self, specular: impl Texture + 'static) -> Self {
+        self.specular = Rc::new(specular);
+        self
+    }
+
+    /// Returns the material with the given transmission texture (0 opaque, 1 glass)
+    pub fn with_transmission(mut self, transmission: impl Texture + 'static) -> Self {
+        self.transmission = Rc::new(transmission);
+        self
+    }
+
+    /// Returns the material with the given clearcoat texture
+    pub fn with_clearcoat(mut self, clearcoat: impl Texture + 'static) -> Self {
+        self.clearcoat = Rc::new(clearcoat);
+        self
+    }
+
+    /// Returns the material with the given sheen texture
+    pub fn with_sheen(mut self, sheen: impl Texture + 'static) -> Self {
+        self.sheen = Rc::new(sheen);
+        self
+    }
+
+    /// Returns the material with the given index of refraction
+    pub fn with_ir(mut self, ir: f64) -> Self {
+        self.ir = ir;
+        self
+    }
+
+    /// Returns the value of a scalar parameter at the hit, between 0 and 1
+    fn scalar(texture: &Rc<dyn Texture>, rec: &HitRecord) -> f64 {
+        clamp(texture.value(rec.u, rec.v, &rec.p).x, 0.0, 1.0)
+    }
+
+    /// Returns the direction reflected around the normal and blurred by the roughness
+    fn glossy(direction: &Vec3, normal: &Vec3, roughness: f64) -> Vec3 {
+        Vec3::reflect(direction, normal) + Vec3::random_in_unit_sphere() * (roughness * roughness)
+    }
+
+    /// Scatters the ray through the glass lobe, reflecting or refracting it according to fresnel
+    fn transmit(
+        &self,
+        unit_direction: &Vec3,
+        rec: &HitRecord,
+        base_color: Color,
+        roughness: f64,
+    ) -> Option<(Ray, Color)> {
+        let refraction_ratio = if rec.front_face {
+            1.0 / self.ir
+        } else {
+            self.ir
+        };
+        let cos_theta = Vec3::dot(&(-unit_direction), &rec.normal).min(1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+
+        if refraction_ratio * sin_theta > 1.0
+            || Dielectric::reflectance(cos_theta, refraction_ratio) > random_float(0.0, 1.0)
+        {
+            let direction = Principled::glossy(unit_direction, &rec.normal, roughness);
+            if Vec3::dot(&direction, &rec.normal) <= 0.0 {
+                return None;
+            }
+            return Some((Ray::create(rec.p, direction), Color::new(1.0, 1.0, 1.0)));
+        }
+
+        let direction = Vec3::refract(unit_direction, &rec.normal, refraction_ratio)
+            + Vec3::random_in_unit_sphere() * (roughness * roughness);
+        if Vec3::dot(&direction, &rec.normal) >= 0.0 {
+            return None;
+        }
+        Some((Ray::create(rec.p, direction), base_color))
+    }
+}
+
+/// Returns the Schlick approximation of the fresnel reflectance
+///
+/// # Arguments
+///
+/// * cosine - The cosine of the angle between the incoming ray and the normal
+/// * r0 - The reflectance at normal incidence
+fn schlick(cosine: f64, r0: f64) -> f64 {
+    r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
+}
+
+impl Material for Principled {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+        let base_color = self.base_color.value(rec.u, rec.v, &rec.p);
+        let roughness = Principled::scalar(&self.roughness, rec);
+
+        let unit_direction = Vec3::unit_vector(&r_in.dir);
+        let cos_theta = clamp(Vec3::dot(&(-unit_direction), &rec.normal), 0.0, 1.0);
+
+        // A ray inside the object can only have got there through the glass lobe
+        if !rec.front_face {
+            return self.transmit(&unit_direction, rec, base_color, roughness);
+        }
+
+        // Clearcoat, a thin and almost smooth dielectric layer with a 4% reflectance
+        let clearcoat = Principled::scalar(&self.clearcoat, rec);
+        if random_float(0.0, 1.0) < clearcoat * schlick(cos_theta, 0.04) {
+            let direction = Principled::glossy(&unit_direction, &rec.normal, 0.1);
+            if Vec3::dot(&direction, &rec.normal) <= 0.0 {
+                return None;
+            }
+            return Some((Ray::create(rec.p, direction), Color::new(1.0, 1.0, 1.0)));
+        }
+
+        // Metal, a reflection tinted by the base color that whitens at grazing angles
+        if random_float(0.0, 1.0) < Principled::scalar(&self.metallic, rec) {
+            let direction = Principled::glossy(&unit_direction, &rec.normal, roughness);
+            if Vec3::dot(&direction, &rec.normal) <= 0.0 {
+                return None;
+            }
+            let white = Color::new(1.0, 1.0, 1.0);
+            let attenuation = base_color + (white - base_color) * (1.0 - cos_theta).powi(5);
+            return Some((Ray::create(rec.p, direction), attenuation));
+        }
+
+        if random_float(0.0, 1.0) < Principled::scalar(&self.transmission, rec) {
+            return self.transmit(&unit_direction, rec, base_color, roughness);
+        }
+
+        // Dielectric specular, an untinted reflection on top of the diffuse base
+        let r0 = 0.08 * Principled::scalar(&self.specular, rec);
+        if random_float(0.0, 1.0) < schlick(cos_theta, r0) {
+            let direction = Principled::glossy(&unit_direction, &rec.normal, roughness);
+            if Vec3::dot(&direction, &rec.normal) > 0.0 {
+                return Some((Ray::create(rec.p, direction), Color::new(1.0, 1.0, 1.0)));
+            }
+        }
+
+        // Diffuse, with the sheen whitening it at grazing angles, blended in so the attenuation
+        // never goes over 1 and the material doesn't create energy
+        let mut scatter_direction = rec.normal + Vec3::random_unit_vec();
+        if scatter_direction.near_zero() {
+            scatter_direction = rec.normal;
+        }
+        let sheen = Principled::scalar(&self.sheen, rec) * (1.0 - cos_theta).powi(5);
+        let attenuation = base_color * (1.0 - sheen) + Color::new(sheen, sheen, sheen);
+
+        Some((Ray::create(rec.p, scatter_direction), attenuation))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{seed_random, vec3::Point3};
+
+    /// Returns the hit at the origin of a ray going in the given direction with the material,
+    /// on a surface whose outward normal is +y
+    fn hit(material: Principled, direction: Vec3) -> (Ray, HitRecord) {
+        let material: Rc<dyn Material> = Rc::new(material);
+        let ray = Ray::create(-direction, direction);
+        let outward_normal = Vec3::new(0.0, 1.0, 0.0);
+        let frame = (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let rec = HitRecord::create(
+            1.0,
+            Point3::zero(),
+            (0.5, 0.5),
+            &material,
+            &outward_normal,
+            frame,
+            &ray,
+        );
+        (ray, rec)
+    }
+
+    #[test]
+    fn schlick_goes_from_the_normal_reflectance_to_one() {
+        assert_eq!(schlick(1.0, 0.04), 0.04);
+        assert_eq!(schlick(0.0, 0.04), 1.0);
+        assert!(schlick(0.5, 0.04) > 0.04);
+    }
+
+    #[test]
+    fn scatters_never_create_energy() {
+        seed_random(1);
+        let material = Principled::new(Color::new(0.9, 0.5, 0.1))
+            .with_metallic(0.3)
+            .with_transmission(0.3)
+            .with_clearcoat(1.0)
+            .with_sheen(1.0)
+            .with_specular(1.0);
+        let (ray, rec) = hit(material, Vec3::new(1.0, -0.2, 0.0));
+        assert!(rec.front_face);
+        for _ in 0..1000 {
+            if let Some((_, attenuation)) = rec.material.scatter(&ray, &rec) {
+                for c in [attenuation.x, attenuation.y, attenuation.z] {
+                    assert!((0.0..=1.0).contains(&c), "{}", c);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn smooth_metal_is_a_tinted_mirror() {
+        seed_random(2);
+        let base_color = Color::new(0.8, 0.6, 0.2);
+        let material = Principled::new(base_color)
+            .with_metallic(1.0)
+            .with_roughness(0.0);
+        let (ray, rec) = hit(material, Vec3::new(0.0, -1.0, 0.0));
+        let (scattered, attenuation) = rec.material.scatter(&ray, &rec).unwrap();
+        assert_eq!(scattered.dir, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(attenuation, base_color);
+    }
+
+    #[test]
+    fn rays_inside_leave_through_the_glass_lobe() {
+        seed_random(3);
+        let base_color = Color::new(0.2, 0.9, 0.4);
+        let material = Principled::new(base_color)
+            .with_transmission(1.0)
+            .with_roughness(0.0);
+        // Straight through the surface there's no total internal reflection
+        let (ray, rec) = hit(material, Vec3::new(0.0, 1.0, 0.0));
+        assert!(!rec.front_face);
+        for _ in 0..100 {
+            if let Some((scattered, attenuation)) = rec.material.scatter(&ray, &rec) {
+                if scattered.dir.y > 0.0 {
+                    assert_eq!(attenuation, base_color);
+                } else {
+                    assert_eq!(attenuation, Color::new(1.0, 1.0, 1.0));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,272 @@
+//! A minimal PNG encoder, the image data is stored without compression so no extra
+//! dependencies are needed
+
+use std::{fs::File, io, io::Write, path::Path};
+
+/// Signature that starts every PNG file
+const SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+/// Biggest block of data a stored deflate block can hold
+const MAX_STORED_BLOCK: usize = 65535;
+
+/// Writes a RGB image with 8 bits per component to a PNG file
+///
+/// # Arguments
+///
+/// * path - Path of the PNG file
+/// * width - Width of the image in pixels
+/// * height - Height of the image in pixels
+/// * rgb - The components of the pixels, from the top left to the bottom right corner
+pub fn write_png(path: &Path, width: usize, height: usize, rgb: &[u8]) -> io::Result<()> {
+    File::create(path)?.write_all(&encode_png(width, height, rgb))
+}
+
+/// Returns the bytes of the PNG file of a RGB image with 8 bits per component, to send it
+/// somewhere else than a file
+///
+/// # Arguments
+///
+/// * width - Width of the image in pixels
+/// * height - Height of the image in pixels
+/// * rgb - The components of the pixels, from the top left to the bottom right corner
+pub fn encode_png(width: usize, height: usize, rgb: &[u8]) -> Vec<u8> {
+    let mut png = SIGNATURE.to_vec();
+    // Writing to a vector can't fail
+    let _ = write_chunk(&mut png, b"IHDR", &header(width, height));
+    let _ = write_chunk(&mut png, b"IDAT", &zlib_stored(&scanlines(width, rgb)));
+    let _ = write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+/// Writes RGB frames with 8 bits per component to an animated PNG (APNG) file that loops
+/// forever, viewers without APNG support show the first frame
+///
+/// # Arguments
+///
+/// * path - Path of the PNG file
+/// * width - Width of the frames in pixels
+/// * height - Height of the frames in pixels
+/// * frames - The components of the pixels of each frame, from the top left to the bottom right corner
+/// * fps - Frames per second
+pub fn write_apng(
+    path: &Path,
+    width: usize,
+    height: usize,
+    frames: &[Vec<u8>],
+    fps: f64,
+) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(&SIGNATURE)?;
+    write_chunk(&mut file, b"IHDR", &header(width, height))?;
+
+    let mut animation_control = Vec::with_capacity(8);
+    animation_control.extend_from_slice(&(frames.len() as u32).to_be_bytes());
+    animation_control.extend_from_slice(&0u32.to_be_bytes());
+    write_chunk(&mut file, b"acTL", &animation_control)?;
+
+    let delay = (1000.0 / fps).round() as u16;
+    let mut sequence_number = 0u32;
+    for (i, frame) in frames.iter().enumerate() {
+        let mut frame_control = Vec::with_capacity(26);
+        frame_control.extend_from_slice(&sequence_number.to_be_bytes());
+        frame_control.extend_from_slice(&(width as u32).to_be_bytes());
+        frame_control.extend_from_slice(&(height as u32).to_be_bytes());
+        frame_control.extend_from_slice(&[0; 8]);
+        frame_control.extend_from_slice(&delay.to_be_bytes());
+        frame_control.extend_from_slice(&1000u16.to_be_bytes());
+        frame_control.extend_from_slice(&[0, 0]);
+        write_chunk(&mut file, b"fcTL", &frame_control)?;
+        sequence_number += 1;
+
+        // The first frame is the default image, the others are only seen in the animation
+        let data = zlib_stored(&scanlines(width, frame));
+        if i == 0 {
+            write_chunk(&mut file, b"IDAT", &data)?;
+        } else {
+            let mut frame_data = Vec::with_capacity(data.len() + 4);
+            frame_data.extend_from_slice(&sequence_number.to_be_bytes());
+            frame_data.extend_from_slice(&data);
+            write_chunk(&mut file, b"fdAT", &frame_data)?;
+            sequence_number += 1;
+        }
+    }
+
+    write_chunk(&mut file, b"IEND", &[])
+}
+
+/// Returns the data of the IHDR chunk for a RGB image with 8 bits per component
+fn header(width: usize, height: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(13);
+    data.extend_from_slice(&(width as u32).to_be_bytes());
+    data.extend_from_slice(&(height as u32).to_be_bytes());
+    // Bit depth 8, color type RGB, default compression, filter and no interlacing
+    data.extend_from_slice(&[8, 2, 0, 0, 0]);
+    data
+}
+
+/// Returns the rows of the image, each one preceded by the "no filter" byte
+fn scanlines(width: usize, rgb: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(rgb.len() + rgb.len() / (width * 3).max(1));
+    for row in rgb.chunks(width * 3) {
+        data.push(0);
+        data.extend_from_slice(row);
+    }
+    data
+}
+
+/// Returns the data wrapped in a zlib stream made of uncompressed deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + data.len() / MAX_STORED_BLOCK * 5 + 11);
+    out.extend_from_slice(&[0x78, 0x01]);
+
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+/// Writes a chunk with its length, type, data and CRC
+fn write_chunk(file: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    file.write_all(&(data.len() as u32).to_be_bytes())?;
+    file.write_all(kind)?;
+    file.write_all(data)?;
+
+    let mut crc = crc32_update(0xffff_ffff, kind);
+    crc = crc32_update(crc, data);
+    file.write_all(&(crc ^ 0xffff_ffff).to_be_bytes())
+}
+
+/// Continues the CRC-32 used by PNG over the given bytes
+fn crc32_update(mut crc: u32, bytes: &[u8]) -> u32 {
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+/// Returns the Adler-32 checksum used by zlib
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+
+    /// Returns the type and the data of the chunks of a PNG file, checking their CRC
+    fn chunks(png: &[u8]) -> Vec<([u8; 4], Vec<u8>)> {
+        assert_eq!(png[..8], SIGNATURE);
+        let mut chunks = Vec::new();
+        let mut rest = &png[8..];
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            let kind = [rest[4], rest[5], rest[6], rest[7]];
+            let data = rest[8..8 + len].to_vec();
+            let crc = crc32_update(crc32_update(0xffff_ffff, &kind), &data) ^ 0xffff_ffff;
+            assert_eq!(rest[8 + len..12 + len], crc.to_be_bytes());
+            chunks.push((kind, data));
+            rest = &rest[12 + len..];
+        }
+        chunks
+    }
+
+    /// Returns the data of a zlib stream made of stored deflate blocks, checking its checksum
+    fn unstore(zlib: &[u8]) -> Vec<u8> {
+        assert_eq!(zlib[..2], [0x78, 0x01]);
+        let mut data = Vec::new();
+        let mut rest = &zlib[2..];
+        loop {
+            let last = rest[0] == 1;
+            let len = u16::from_le_bytes([rest[1], rest[2]]) as usize;
+            assert_eq!(u16::from_le_bytes([rest[3], rest[4]]), !(len as u16));
+            data.extend_from_slice(&rest[5..5 + len]);
+            rest = &rest[5 + len..];
+            if last {
+                break;
+            }
+        }
+        assert_eq!(rest, adler32(&data).to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn checksums_match_the_reference_values() {
+        assert_eq!(
+            crc32_update(0xffff_ffff, b"123456789") ^ 0xffff_ffff,
+            0xcbf4_3926
+        );
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn images_have_the_header_and_the_rows() {
+        let rgb: Vec<u8> = (0..2 * 2 * 3).collect();
+        let png = chunks(&encode_png(2, 2, &rgb));
+        let kinds: Vec<&[u8; 4]> = png.iter().map(|(kind, _)| kind).collect();
+        assert_eq!(kinds, [b"IHDR", b"IDAT", b"IEND"]);
+        assert_eq!(png[0].1, [0, 0, 0, 2, 0, 0, 0, 2, 8, 2, 0, 0, 0]);
+        assert_eq!(
+            unstore(&png[1].1),
+            [0, 0, 1, 2, 3, 4, 5, 0, 6, 7, 8, 9, 10, 11]
+        );
+    }
+
+    #[test]
+    fn big_images_are_split_in_blocks() {
+        let rgb: Vec<u8> = (0..300 * 300 * 3).map(|i| i as u8).collect();
+        let png = chunks(&encode_png(300, 300, &rgb));
+        let data = unstore(&png[1].1);
+        assert_eq!(data.len(), 300 * (300 * 3 + 1));
+        assert_eq!(data[901], 0);
+        assert_eq!(data[902..1802], rgb[900..1800]);
+        assert_eq!(unstore(&zlib_stored(&[])), []);
+    }
+
+    #[test]
+    fn animations_have_a_numbered_control_chunk_per_frame() {
+        let path = env::temp_dir().join(format!("ray_tracer_{}_animation.png", process::id()));
+        let frames = vec![vec![0u8; 3], vec![128u8; 3], vec![255u8; 3]];
+        write_apng(&path, 1, 1, &frames, 25.0).unwrap();
+        let png = chunks(&fs::read(&path).unwrap());
+        fs::remove_file(&path).unwrap();
+
+        let kinds: Vec<&[u8; 4]> = png.iter().map(|(kind, _)| kind).collect();
+        assert_eq!(
+            kinds,
+            [b"IHDR", b"acTL", b"fcTL", b"IDAT", b"fcTL", b"fdAT", b"fcTL", b"fdAT", b"IEND"]
+        );
+        // Three frames that loop forever
+        assert_eq!(png[1].1, [0, 0, 0, 3, 0, 0, 0, 0]);
+        // The sequence numbers are shared by the frame controls and the frame data
+        let number = |data: &[u8]| u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+        let numbers: Vec<u32> = [2, 4, 5, 6, 7].iter().map(|&i| number(&png[i].1)).collect();
+        assert_eq!(numbers, [0, 1, 2, 3, 4]);
+        // Each frame lasts 40ms
+        assert_eq!(png[2].1[20..24], [0, 40, 3, 232]);
+        assert_eq!(unstore(&png[3].1), [0, 0, 0, 0]);
+        assert_eq!(unstore(&png[7].1[4..]), [0, 255, 255, 255]);
+    }
+}
@@ -1,5 +1,7 @@
 use crate::color::Color;
 use crate::hittable::Hittable;
+use crate::spectrum::{rgb_to_spectrum, sample_wavelength, spectral_sample_to_rgb};
+use crate::stats::{count_ray, RayKind};
 use crate::vec3::{Point3, Vec3};
 use crate::MAX;
 
@@ -8,6 +10,10 @@ use crate::MAX;
 pub struct Ray {
     pub origin: Point3,
     pub dir: Vec3,
+    /// Wavelength in nanometers carried by the ray when rendering in spectral mode
+    pub wavelength: Option<f64>,
+    /// Moment, in seconds, in which the ray exists, used by moving objects for motion blur
+    pub time: f64,
 }
 
 impl Ray {
@@ -18,7 +24,23 @@ impl Ray {
     /// * origin - Point from where the ray was casted
     /// * dir - The direction of the ray
     pub fn create(origin: Point3, dir: Vec3) -> Ray {
-        Ray { origin, dir }
+        Ray::with_time(origin, dir, 0.0)
+    }
+
+    ///Returns a ray catested from a given point in a given direction at a given moment
+    ///
+    /// # Arguments
+    ///
+    /// * origin - Point from where the ray was casted
+    /// * dir - The direction of the ray
+    /// * time - Moment, in seconds, in which the ray exists
+    pub fn with_time(origin: Point3, dir: Vec3, time: f64) -> Ray {
+        Ray {
+            origin,
+            dir,
+            wavelength: None,
+            time,
+        }
     }
 
     /// Returns the point the ray reached after a given time
@@ -27,7 +49,7 @@ impl Ray {
     ///
     /// * time - The time passed since the ray was casted
     pub fn at(&self, time: f64) -> Point3 {
-        let v = self.clone();
+        let v = *self;
         v.origin + v.dir * time
     }
 }
@@ -40,17 +62,60 @@ impl Ray {
 /// * world - The hittable_list that contains all the hittables the ray may hit
 /// * depth - Maximum number of refractions/reflections a ray can have
 pub fn ray_color(ray: &Ray, world: &dyn Hittable, depth: i32) -> Color {
+    trace(ray, world, depth, &|color| color)
+}
+
+/// Returns the color of the casted ray after tracing it with a single random wavelength,
+/// so the materials that depend on the wavelength can split the light
+///
+/// # Arguments
+///
+/// * ray - The ray to detect it's color
+/// * world - The hittable_list that contains all the hittables the ray may hit
+/// * depth - Maximum number of refractions/reflections a ray can have
+pub fn ray_color_spectral(ray: &Ray, world: &dyn Hittable, depth: i32) -> Color {
+    let lambda = sample_wavelength();
+    let r = Ray {
+        wavelength: Some(lambda),
+        ..*ray
+    };
+    let to_spectrum = |color: Color| {
+        let value = rgb_to_spectrum(&color, lambda);
+        Color::new(value, value, value)
+    };
+
+    spectral_sample_to_rgb(trace(&r, world, depth, &to_spectrum).x, lambda)
+}
+
+/// Follows the ray through the world and returns the product of the colors it collected
+///
+/// # Arguments
+///
+/// * ray - The ray to trace
+/// * world - The hittable_list that contains all the hittables the ray may hit
+/// * depth - Maximum number of refractions/reflections a ray can have
+/// * convert - Function applied to every color before it's multiplied into the result
+fn trace(ray: &Ray, world: &dyn Hittable, depth: i32, convert: &dyn Fn(Color) -> Color) -> Color {
     if depth == 0 {
         return Color::zero();
     }
     let mut color = Color::new(1.0, 1.0, 1.0);
-    let mut r = ray.clone();
-    for _ in 0..depth {
+    let mut r = *ray;
+    for bounce in 0..depth {
+        count_ray(if bounce == 0 {
+            RayKind::Primary
+        } else {
+            RayKind::Secondary
+        });
         match world.hit(&r, 0.001, MAX) {
             Some(rec) => match rec.material.scatter(&r, &rec) {
                 Some((scattered, attenuation)) => {
-                    r = scattered;
-                    color = color * attenuation;
+                    r = Ray {
+                        wavelength: r.wavelength,
+                        time: r.time,
+                        ..scattered
+                    };
+                    color = color * convert(attenuation);
                 }
                 None => {
                     color = Color::zero();
@@ -60,8 +125,10 @@ pub fn ray_color(ray: &Ray, world: &dyn Hittable, depth: i32) -> Color {
             None => {
                 let unit_dir = Vec3::unit_vector(&r.dir);
                 let t = 0.5 * (unit_dir.y + 1.0);
-                color =
-                    color * (Color::new(1.0, 1.0, 1.0) * (1.0 - t) + Color::new(0.5, 0.7, 1.0) * t);
+                color = color
+                    * convert(
+                        Color::new(1.0, 1.0, 1.0) * (1.0 - t) + Color::new(0.5, 0.7, 1.0) * t,
+                    );
                 break;
             }
         }
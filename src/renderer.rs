@@ -1,10 +1,349 @@
-use std::{fs::File, io::Write, path::Path};
+mod checkpoint;
+mod crop;
+mod progress;
 
+pub use {
+    checkpoint::Checkpoint,
+    crop::CropWindow,
+    progress::{CancellationToken, ConsoleProgress, Progress, ProgressObserver},
+};
+
+use progress::SequenceProgress;
+pub(crate) use progress::{Goal, ProgressTracker};
+use std::{
+    io,
+    path::Path,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use crate::animation::{AnimatedCamera, FrameSequence, SequenceOutput};
 use crate::camera::Camera;
-use crate::color::{write_color, Color};
+use crate::color::Color;
+use crate::filter::Filter;
+use crate::framebuffer::Framebuffer;
 use crate::hittable::Hittable;
-use crate::ray::ray_color;
-use rand::prelude::*;
+use crate::png::write_apng;
+use crate::ray::{ray_color, ray_color_spectral};
+use crate::stats::{take_counters, RenderStats, TileStats};
+use crate::{random_float, seed_random};
+
+/// Represents how the samples are concentrated where the image is noisy
+#[derive(Clone, Copy, Debug)]
+pub struct AdaptiveSampling {
+    /// Samples every pixel gets before its noise is estimated
+    pub min_samples: u32,
+    /// Samples after which a pixel stops even if it's still noisy
+    pub max_samples: u32,
+    /// Noise, relative to the pixel's luminance, under which a pixel stops getting samples
+    pub noise_threshold: f64,
+}
+
+/// Represents when a render stops casting samples
+#[derive(Clone, Copy, Debug)]
+pub enum StopCondition {
+    /// Every pixel gets the given number of samples, with adaptive sampling it's the most
+    /// samples a pixel can get
+    Samples(u32),
+    /// Passes are rendered until the time runs out, each pass is shortened to the samples per
+    /// pixel that fit in the time left at the speed of the previous ones, so every pass is
+    /// finished and all the pixels get the same samples
+    Time(Duration),
+    /// Passes are rendered until the average noise of the pixels, relative to their luminance,
+    /// is under the threshold, once every pixel has enough samples to estimate it, or until
+    /// every pixel has `max_samples` if the threshold can't be reached
+    Noise { threshold: f64, max_samples: u32 },
+}
+
+/// Fewest samples of every pixel before the noise of the image is trusted to stop a render
+const MIN_NOISE_SAMPLES: u32 = 16;
+
+/// Most samples per pixel of a pass, so the previews and the checks of the stop condition
+/// keep coming in long renders
+const MAX_PASS_SAMPLES: u32 = 64;
+
+/// Width and height in pixels of the tiles a pass renders one after the other
+const TILE_SIZE: usize = 32;
+
+/// Represents the settings of a render
+#[derive(Clone)]
+pub struct RenderSettings {
+    /// The width in pixels of the image to be rendered
+    pub image_width: i32,
+    /// The height in pixels of the image to be rendered
+    pub image_height: i32,
+    /// When the render stops casting samples
+    pub stop: StopCondition,
+    /// The maximum number of bounces a ray can have
+    pub max_depth: i32,
+    /// Traces each ray with a single wavelength so dispersive materials split the light
+    pub spectral: bool,
+    /// Name of a ppm file, without the extension, that is rewritten after each pass so the
+    /// render can be previewed while it refines
+    pub progressive_output: Option<String>,
+    /// Gives each pixel between a minimum and a maximum number of samples depending on its
+    /// noise, instead of the same number to all of them
+    pub adaptive: Option<AdaptiveSampling>,
+    /// Seed of the random numbers, each sample is seeded from it, its pixel and its index so
+    /// the same seed renders the same image
+    pub seed: u64,
+    /// File where the samples are periodically saved and from which the render resumes
+    pub checkpoint: Option<Checkpoint>,
+    /// Renders only a region of the image, the framebuffer then has the size of the region
+    pub crop: Option<CropWindow>,
+    /// How the samples are weighted to reconstruct the pixels
+    pub filter: Filter,
+    /// Receives the progress of the render
+    pub observer: Option<Rc<dyn ProgressObserver>>,
+    /// Stops the render when it's cancelled, the samples cast until then are returned
+    pub cancellation: Option<CancellationToken>,
+}
+
+impl RenderSettings {
+    /// Returns the settings to render a whole image of the given size with 100 samples per
+    /// pixel and up to 50 bounces, with the seed 0, a box filter that averages the samples of
+    /// each pixel, without progressive output or checkpoints and printing the progress to the
+    /// standard error
+    ///
+    /// # Arguments
+    ///
+    /// * image_width - The width in pixels of the image to be rendered
+    /// * image_height - The height in pixels of the image to be rendered
+    pub fn new(image_width: i32, image_height: i32) -> Self {
+        Self {
+            image_width,
+            image_height,
+            stop: StopCondition::Samples(100),
+            max_depth: 50,
+            spectral: false,
+            progressive_output: None,
+            adaptive: None,
+            seed: 0,
+            checkpoint: None,
+            crop: None,
+            filter: Filter::default(),
+            observer: Some(Rc::new(ConsoleProgress)),
+            cancellation: None,
+        }
+    }
+}
+
+/// Renders the image of the world from the view of the camera in progressive passes of 1, 2,
+/// 4, ... samples per pixel over the whole image, so a rough version of the full image is
+/// available early and refines with each pass
+///
+/// Passes have at most 64 samples per pixel and are rendered until the stop condition is met.
+/// With adaptive sampling the passes skip the pixels whose noise is already under the
+/// threshold, the number of samples each pixel got is kept in the framebuffer. With a
+/// checkpoint the render resumes from its file if it exists and saves to it periodically and
+/// when it's done, resuming gives the same image as a render that wasn't interrupted. A
+/// cancelled render stops at the end of the tile in progress and returns what it has. Each
+/// sample is splatted to the pixels the filter reaches, a crop window also renders the pixels
+/// around it whose samples reach it so its edges look like in the full image.
+///
+/// The render fails with an `InvalidInput` error if the crop window has no pixels inside the
+/// image or the checkpoint was saved by a render with other settings, and with the error that
+/// reading the checkpoint gave if it can't be read.
+///
+/// # Arguments
+///
+/// * settings - The size of the image, the number of samples and where to preview the passes
+/// * camera - The camera that will cast the rays
+/// * world - The hittable list that contains all the hittables that the ray can bouce off
+pub fn render(
+    settings: &RenderSettings,
+    camera: &dyn Camera,
+    world: &dyn Hittable,
+) -> io::Result<Framebuffer> {
+    Ok(render_with_stats(settings, camera, world)?.0)
+}
+
+/// Renders the image like `render` and also returns the statistics of the render: the rays
+/// traced, the intersection tests, the time and the time spent in each tile
+///
+/// # Arguments
+///
+/// * settings - The size of the image, the number of samples and where to preview the passes
+/// * camera - The camera that will cast the rays
+/// * world - The hittable list that contains all the hittables that the ray can bouce off
+pub fn render_with_stats(
+    settings: &RenderSettings,
+    camera: &dyn Camera,
+    world: &dyn Hittable,
+) -> io::Result<(Framebuffer, RenderStats)> {
+    let start = Instant::now();
+    // Discards the work counted in this thread before the render
+    take_counters();
+    let max_samples = match settings.stop {
+        StopCondition::Samples(samples) => samples,
+        StopCondition::Noise { max_samples, .. } => max_samples,
+        StopCondition::Time(_) => u32::MAX,
+    };
+    let adaptive = match settings.adaptive {
+        Some(adaptive) => AdaptiveSampling {
+            max_samples: adaptive.max_samples.min(max_samples),
+            ..adaptive
+        },
+        None => AdaptiveSampling {
+            min_samples: max_samples,
+            max_samples,
+            noise_threshold: 0.0,
+        },
+    };
+    let deadline = match settings.stop {
+        StopCondition::Time(duration) => Some(Instant::now() + duration),
+        _ => None,
+    };
+    let crop = crop_window(settings)?;
+    let region = render_region(settings, crop);
+    let mut framebuffer =
+        Framebuffer::new(region.width, region.height).with_filter(settings.filter);
+    let mut tiles = tiles(region);
+    // Passes bring the pixels that aren't done to 1, 3, 7, ... samples, so a pass that was
+    // interrupted is finished by the render that resumes it
+    let mut reached = 0u32;
+    let mut pass_samples = 1;
+    let checkpoint_settings = checkpoint_settings(settings, crop);
+
+    if let Some(checkpoint) = &settings.checkpoint {
+        let saved = checkpoint
+            .load(&checkpoint_settings, region.width, region.height)
+            .map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("couldn't resume from {}: {}", checkpoint.path.display(), e),
+                )
+            })?;
+        if let Some(saved) = saved {
+            framebuffer = saved.framebuffer.with_filter(settings.filter);
+            reached = saved.reached;
+            pass_samples = saved.pass_samples;
+        }
+    }
+    let mut last_save = Instant::now();
+    let mut tracker = ProgressTracker::new(
+        settings.observer.clone(),
+        match settings.stop {
+            StopCondition::Time(duration) => Goal::Time(duration),
+            _ => Goal::Noise(0.0),
+        },
+    );
+
+    // Samples cast by this render and the time spent casting them, to fit the passes in the
+    // time left
+    let (mut cast, mut casting) = (0, Duration::default());
+
+    loop {
+        let mut target = reached.saturating_add(pass_samples);
+        let mut plan = plan_pass(&framebuffer, &adaptive, target);
+        if let Some(deadline) = deadline {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                break;
+            }
+            // The first pass has a single sample per pixel to measure the speed
+            let pixels = plan.iter().filter(|&&n| n > 0).count().max(1) as f64;
+            let speed = cast as f64 / casting.as_secs_f64().max(1e-9);
+            let fit = match cast {
+                0 => 1,
+                _ => (speed * left.as_secs_f64() / pixels).min(u32::MAX as f64) as u32,
+            };
+            if fit == 0 {
+                break;
+            }
+            if fit < pass_samples {
+                target = reached.saturating_add(fit);
+                plan = plan_pass(&framebuffer, &adaptive, target);
+            }
+        }
+        if plan.iter().all(|&n| n == 0) {
+            break;
+        }
+        if let StopCondition::Samples(_) = settings.stop {
+            // Pixels that aren't done are expected to get all the samples they can
+            let target = (0..plan.len())
+                .map(|i| match plan[i] {
+                    0 => framebuffer.samples(i % framebuffer.width(), i / framebuffer.width()),
+                    _ => adaptive.max_samples,
+                } as u64)
+                .sum();
+            tracker.set_goal(Goal::Samples {
+                done: framebuffer.total_samples(),
+                target,
+            });
+        }
+
+        let pass_start = Instant::now();
+        let mut finished = true;
+        for tile in tiles.iter_mut() {
+            if is_cancelled(settings) {
+                finished = false;
+                break;
+            }
+            let samples = render_tile(
+                &mut framebuffer,
+                region,
+                &plan,
+                tile,
+                settings,
+                camera,
+                world,
+            );
+            tracker.add_samples(samples);
+            cast += samples;
+        }
+        casting += pass_start.elapsed();
+        if finished {
+            reached = target;
+            pass_samples = (pass_samples * 2).min(MAX_PASS_SAMPLES);
+        }
+
+        if let Some(checkpoint) = &settings.checkpoint {
+            if last_save.elapsed() >= checkpoint.interval {
+                let state = (reached, pass_samples);
+                save_checkpoint(checkpoint, &checkpoint_settings, state, &framebuffer);
+                last_save = Instant::now();
+            }
+        }
+
+        if let Some(name) = &settings.progressive_output {
+            if let Err(e) = crop_margin(&framebuffer, crop, region).write_ppm(name) {
+                eprintln!("couldn't write {}.ppm: {}", name, e);
+            }
+        }
+
+        let stop = match settings.stop {
+            StopCondition::Samples(_) | StopCondition::Time(_) => false,
+            StopCondition::Noise { threshold, .. } => {
+                // The noise falls with the square root of the samples
+                let noise = framebuffer.average_noise();
+                let pixels = (framebuffer.width() * framebuffer.height()).max(1) as f64;
+                let samples = framebuffer.total_samples() as f64 / (pixels * max_samples as f64);
+                tracker.set_goal(Goal::Noise((threshold / noise).powi(2).max(samples)));
+                framebuffer.sample_range().0 >= MIN_NOISE_SAMPLES && noise <= threshold
+            }
+        };
+        if stop || is_cancelled(settings) {
+            break;
+        }
+    }
+
+    if let Some(checkpoint) = &settings.checkpoint {
+        let state = (reached, pass_samples);
+        save_checkpoint(checkpoint, &checkpoint_settings, state, &framebuffer);
+    }
+    tracker.finish();
+
+    let stats = RenderStats {
+        counters: take_counters(),
+        time: start.elapsed(),
+        tiles,
+    };
+    if region != crop {
+        framebuffer = crop_margin(&framebuffer, crop, region);
+    }
+    Ok((framebuffer, stats))
+}
 
 /// Renders the image of the world from the view of the camera and stores it in a ppm file
 ///
@@ -13,49 +352,651 @@ use rand::prelude::*;
 /// * image_width - The width in pixels of the image to be rendered
 /// * image_height - The height in pixels of the image to be rendered
 /// * image_name - The name to give to the ppm file
-/// * samples_per_pixel - The number of rays that will be cast per pixel
+/// * stop - When the render stops casting samples
 /// * max_depth - The maximum number of bounces a ray can have
 /// * camera - The camera that will cast the rays
 /// * world - The hittable list that contains all the hittables that the ray can bouce off
+///
+/// Returns the framebuffer, which has the number of samples each pixel ended up with
 pub fn render_image(
     image_width: i32,
     image_height: i32,
     image_name: String,
-    samples_per_pixel: i32,
+    stop: StopCondition,
     max_depth: i32,
-    camera: &Camera,
+    camera: &dyn Camera,
     world: &dyn Hittable,
-) {
-    let f = create_image(image_width, image_height, image_name);
+) -> Framebuffer {
+    let settings = RenderSettings {
+        stop,
+        max_depth,
+        ..RenderSettings::new(image_width, image_height)
+    };
+    let framebuffer = render(&settings, camera, world)
+        .unwrap_or_else(|e| panic!("couldn't render {}: {}", image_name, e));
+    write_image(&framebuffer, &image_name);
+    framebuffer
+}
 
-    let mut rng = thread_rng();
+/// Renders the image like `render_image`, but tracing each ray with a single wavelength so
+/// dispersive materials split the light into its colors
+///
+/// # Arguments
+///
+/// * image_width - The width in pixels of the image to be rendered
+/// * image_height - The height in pixels of the image to be rendered
+/// * image_name - The name to give to the ppm file
+/// * stop - When the render stops casting samples
+/// * max_depth - The maximum number of bounces a ray can have
+/// * camera - The camera that will cast the rays
+/// * world - The hittable list that contains all the hittables that the ray can bouce off
+///
+/// Returns the framebuffer, which has the number of samples each pixel ended up with
+pub fn render_spectral_image(
+    image_width: i32,
+    image_height: i32,
+    image_name: String,
+    stop: StopCondition,
+    max_depth: i32,
+    camera: &dyn Camera,
+    world: &dyn Hittable,
+) -> Framebuffer {
+    let settings = RenderSettings {
+        stop,
+        max_depth,
+        spectral: true,
+        ..RenderSettings::new(image_width, image_height)
+    };
+    let framebuffer = render(&settings, camera, world)
+        .unwrap_or_else(|e| panic!("couldn't render {}: {}", image_name, e));
+    write_image(&framebuffer, &image_name);
+    framebuffer
+}
 
-    for j in (0..image_height).rev() {
-        eprintln!("Scanlines remaining: {}", j);
-        for i in 0..image_width {
-            let mut color = Color::zero();
+/// Renders the frames of an animation and stores them in numbered png files or in an
+/// animated png file, depending on the sequence's output
+///
+/// Each frame is rendered with the settings and a seed derived from the settings' seed and the
+/// frame number, so the noise of the frames isn't the same. The frames don't use the
+/// checkpoint of the settings, and the observer gets the progress of the whole sequence. A
+/// cancelled sequence stops without writing the frame in progress.
+///
+/// # Arguments
+///
+/// * settings - The size of the frames, the number of samples and who gets the progress
+/// * camera - The keyframed camera that will cast the rays
+/// * world - The hittable list that contains all the hittables that the ray can bouce off,
+///   animated hittables move with the time of each frame
+/// * sequence - The frames to render, their timing and the name of the files
+pub fn render_sequence(
+    settings: &RenderSettings,
+    camera: &mut AnimatedCamera,
+    world: &dyn Hittable,
+    sequence: &FrameSequence,
+) -> io::Result<()> {
+    let start = Instant::now();
+    let count = (sequence.first..=sequence.last).count();
+    let mut frames = Vec::new();
+    let mut size = (0, 0);
 
-            for _ in 0..samples_per_pixel {
-                let u = (i as f64 + rng.gen::<f64>()) / (image_width - 1) as f64;
-                let v = (j as f64 + rng.gen::<f64>()) / (image_height - 1) as f64;
+    for (index, frame) in (sequence.first..=sequence.last).enumerate() {
+        let observer = settings.observer.clone().map(|observer| {
+            Rc::new(SequenceProgress::new(observer, start, index, count))
+                as Rc<dyn ProgressObserver>
+        });
+        let frame_settings = RenderSettings {
+            seed: frame_seed(settings.seed, frame),
+            checkpoint: None,
+            observer,
+            ..settings.clone()
+        };
+        let frame_camera = camera.at(sequence.time(frame), sequence.shutter_interval(frame));
+        let framebuffer = render(&frame_settings, frame_camera, world)?;
+        if is_cancelled(settings) {
+            return Ok(());
+        }
 
-                let r = camera.get_ray(u, v);
-                color += ray_color(&r, world, max_depth);
+        match sequence.output {
+            SequenceOutput::Frames => {
+                let file_name = sequence.file_name(frame);
+                framebuffer
+                    .write_png(Path::new(&file_name))
+                    .map_err(|e| write_error(&file_name, e))?;
+            }
+            SequenceOutput::AnimatedPng => {
+                size = (framebuffer.width(), framebuffer.height());
+                frames.push(framebuffer.to_rgb8());
             }
-            write_color(&color, samples_per_pixel, &f);
         }
     }
+
+    if let SequenceOutput::AnimatedPng = sequence.output {
+        let file_name = sequence.animation_file_name();
+        write_apng(Path::new(&file_name), size.0, size.1, &frames, sequence.fps)
+            .map_err(|e| write_error(&file_name, e))?;
+    }
+    Ok(())
+}
+
+/// Returns the error with the name of the file that couldn't be written
+fn write_error(file_name: &str, error: io::Error) -> io::Error {
+    io::Error::new(
+        error.kind(),
+        format!("couldn't write {}: {}", file_name, error),
+    )
 }
 
-fn create_image(image_width: i32, image_height: i32, image_name: String) -> File {
-    let file_name = format!("{}.ppm", image_name);
-    let path = Path::new(&file_name);
-    match File::create(&path) {
-        Err(e) => panic!("couldn't create {}: {}", path.display(), e),
-        Ok(mut file) => {
-            file.write_all(format!("P3\n{} {}\n255\n", image_width, image_height).as_bytes())
-                .expect("Could not write line");
-            file
+/// Returns the number of samples each pixel gets in the next pass, from the top left to the
+/// bottom right corner
+///
+/// # Arguments
+///
+/// * framebuffer - The samples cast so far
+/// * adaptive - The bounds of the samples per pixel and the noise at which a pixel stops
+/// * target - The samples a pixel that isn't done has at the end of this pass
+fn plan_pass(framebuffer: &Framebuffer, adaptive: &AdaptiveSampling, target: u32) -> Vec<u32> {
+    let (width, height) = (framebuffer.width(), framebuffer.height());
+    let mut plan = Vec::with_capacity(width * height);
+
+    for y in 0..height {
+        for x in 0..width {
+            let samples = framebuffer.samples(x, y);
+            let done = samples >= adaptive.max_samples
+                || (samples >= adaptive.min_samples
+                    && framebuffer.noise(x, y) <= adaptive.noise_threshold);
+            plan.push(if done {
+                0
+            } else {
+                target.min(adaptive.max_samples).saturating_sub(samples)
+            });
         }
     }
+
+    plan
+}
+
+/// Adds the planned number of samples to each pixel of a tile, adding the time it took and
+/// the samples to the tile's statistics, returns the number of samples cast
+///
+/// Each sample reseeds the random numbers with `sample_seed`, so a sample is the same no
+/// matter in which pass or tile it's cast.
+fn render_tile(
+    framebuffer: &mut Framebuffer,
+    region: CropWindow,
+    plan: &[u32],
+    tile: &mut TileStats,
+    settings: &RenderSettings,
+    camera: &dyn Camera,
+    world: &dyn Hittable,
+) -> u64 {
+    let start = Instant::now();
+    let color_fn = if settings.spectral {
+        ray_color_spectral
+    } else {
+        ray_color
+    };
+    let (image_width, image_height) = (
+        settings.image_width as usize,
+        settings.image_height as usize,
+    );
+    let width = framebuffer.width();
+    let window = tile.window;
+    let mut cast = 0;
+
+    for y in window.y - region.y..window.y - region.y + window.height {
+        // Rows are counted from the top, the camera's t goes from the bottom to the top
+        let j = image_height - 1 - (region.y + y);
+        for x in window.x - region.x..window.x - region.x + window.width {
+            let i = region.x + x;
+            // Samples are seeded with the pixel of the full image so crops match full renders
+            let pixel = (region.y + y) * image_width + i;
+            for _ in 0..plan[y * width + x] {
+                seed_random(sample_seed(settings.seed, pixel, framebuffer.samples(x, y)));
+                let (s, t) = (random_float(0.0, 1.0), random_float(0.0, 1.0));
+                let u = (i as f64 + s) / (image_width - 1) as f64;
+                let v = (j as f64 + t) / (image_height - 1) as f64;
+
+                let mut color = Color::zero();
+                for (r, weight) in camera.get_weighted_rays(u, v) {
+                    color += color_fn(&r, world, settings.max_depth) * weight;
+                }
+                // t grows upwards and the rows downwards
+                framebuffer.add_sample(x, y, (s - 0.5, 0.5 - t), color);
+            }
+            cast += plan[y * width + x] as u64;
+        }
+    }
+
+    tile.time += start.elapsed();
+    tile.samples += cast;
+    cast
+}
+
+/// Returns the tiles that cover the region of the image, from the top left to the bottom
+/// right corner, with no time or samples yet
+///
+/// The tiles are the parts inside the region of the tiles of the full image, so the samples
+/// a filter splats to a pixel are added in the same order in a crop window as in the full
+/// image, and give the same bits.
+fn tiles(crop: CropWindow) -> Vec<TileStats> {
+    let (right, bottom) = (crop.x + crop.width, crop.y + crop.height);
+    let mut tiles = Vec::new();
+    for y in (crop.y - crop.y % TILE_SIZE..bottom).step_by(TILE_SIZE) {
+        for x in (crop.x - crop.x % TILE_SIZE..right).step_by(TILE_SIZE) {
+            let (left, top) = (x.max(crop.x), y.max(crop.y));
+            tiles.push(TileStats {
+                window: CropWindow::new(
+                    left,
+                    top,
+                    (x + TILE_SIZE).min(right) - left,
+                    (y + TILE_SIZE).min(bottom) - top,
+                ),
+                time: Duration::default(),
+                samples: 0,
+            });
+        }
+    }
+    tiles
+}
+
+/// Returns the region of the image the render covers, the whole image without a crop window,
+/// or an `InvalidInput` error if the crop window has no pixels inside the image
+fn crop_window(settings: &RenderSettings) -> io::Result<CropWindow> {
+    let (width, height) = (
+        settings.image_width.max(0) as usize,
+        settings.image_height.max(0) as usize,
+    );
+    match settings.crop {
+        Some(crop) => crop.try_clip(width, height),
+        None => Ok(CropWindow::new(0, 0, width, height)),
+    }
+}
+
+/// Returns the region of the image whose samples are cast, the crop window and the pixels
+/// around it whose samples the filter splats into it
+fn render_region(settings: &RenderSettings, crop: CropWindow) -> CropWindow {
+    let reach = settings.filter.reach();
+    let (x, y) = (crop.x.saturating_sub(reach), crop.y.saturating_sub(reach));
+    CropWindow::new(
+        x,
+        y,
+        crop.x + crop.width + reach - x,
+        crop.y + crop.height + reach - y,
+    )
+    .clip(
+        settings.image_width as usize,
+        settings.image_height as usize,
+    )
+}
+
+/// Returns the pixels of the crop window from the framebuffer of the region that was rendered
+fn crop_margin(framebuffer: &Framebuffer, crop: CropWindow, region: CropWindow) -> Framebuffer {
+    framebuffer.crop(
+        crop.x - region.x,
+        crop.y - region.y,
+        crop.width,
+        crop.height,
+    )
+}
+
+/// Returns whether the render was cancelled
+fn is_cancelled(settings: &RenderSettings) -> bool {
+    settings
+        .cancellation
+        .as_ref()
+        .is_some_and(CancellationToken::is_cancelled)
+}
+
+/// Returns the seed of the random numbers of a sample
+///
+/// # Arguments
+///
+/// * seed - Seed of the render
+/// * pixel - Index of the pixel, from the top left to the bottom right corner
+/// * sample - Index of the sample in the pixel
+fn sample_seed(seed: u64, pixel: usize, sample: u32) -> u64 {
+    mix_bits(seed ^ mix_bits(((pixel as u64) << 32) | sample as u64))
+}
+
+/// Returns the seed of the render of a frame of a sequence
+///
+/// # Arguments
+///
+/// * seed - Seed of the sequence
+/// * frame - Number of the frame
+fn frame_seed(seed: u64, frame: u32) -> u64 {
+    mix_bits(seed ^ mix_bits(frame as u64))
+}
+
+/// Returns the bits scrambled with the finalizer of SplitMix64, so close inputs give unrelated
+/// outputs
+fn mix_bits(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Returns a description of the settings that change the samples of a render, a checkpoint
+/// only resumes a render with the same description
+///
+/// # Arguments
+///
+/// * settings - The settings of the render
+/// * crop - The region of the image the render covers
+fn checkpoint_settings(settings: &RenderSettings, crop: CropWindow) -> String {
+    format!(
+        "{}x{} pixels, seed {}, stop {:?}, adaptive {:?}, spectral {}, max depth {}, crop {:?}, \
+         filter {} {}",
+        settings.image_width,
+        settings.image_height,
+        settings.seed,
+        settings.stop,
+        settings.adaptive,
+        settings.spectral,
+        settings.max_depth,
+        settings.crop.map(|_| crop),
+        settings.filter.kind(),
+        settings.filter.radius()
+    )
+}
+
+/// Saves the samples to the checkpoint, a failed save only warns so the render goes on
+///
+/// # Arguments
+///
+/// * checkpoint - Where the samples are saved
+/// * settings - Description of the settings of the render that change its samples
+/// * (reached, pass_samples) - Samples per pixel after the last finished pass and samples per
+///   pixel of the next pass
+/// * framebuffer - Samples accumulated so far
+fn save_checkpoint(
+    checkpoint: &Checkpoint,
+    settings: &str,
+    (reached, pass_samples): (u32, u32),
+    framebuffer: &Framebuffer,
+) {
+    if let Err(e) = checkpoint.save(settings, reached, pass_samples, framebuffer) {
+        eprintln!("couldn't save {}: {}", checkpoint.path.display(), e);
+    }
+}
+
+/// Writes the framebuffer to a ppm file, panicking if it can't be written
+fn write_image(framebuffer: &Framebuffer, image_name: &str) {
+    if let Err(e) = framebuffer.write_ppm(image_name) {
+        panic!("couldn't write {}.ppm: {}", image_name, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        camera::PerspectiveCamera,
+        color::read_ppm,
+        filter::FilterKind,
+        hittable::{HittableList, Sphere},
+        material::Lambertian,
+        vec3::Point3,
+    };
+    use std::{cell::Cell, env, fs, path::PathBuf, process};
+
+    /// Records whether the render reported its end
+    struct Finished(Rc<Cell<bool>>);
+
+    impl ProgressObserver for Finished {
+        fn progress(&self, _: &Progress) {}
+
+        fn finished(&self, _: &Progress) {
+            self.0.set(true);
+        }
+    }
+
+    /// Returns a small sphere resting on a big one
+    fn world() -> HittableList {
+        let mut world = HittableList::new();
+        let gray = Lambertian::new(Color::new(0.5, 0.5, 0.5));
+        world.add(Sphere::create(Point3::new(0.0, -100.5, -1.0), 100.0, gray));
+        let red = Lambertian::new(Color::new(0.7, 0.3, 0.3));
+        world.add(Sphere::create(Point3::new(0.0, 0.0, -1.0), 0.5, red));
+        world
+    }
+
+    /// Returns a camera that looks at the small sphere
+    fn camera() -> PerspectiveCamera {
+        PerspectiveCamera::builder().aspect_ratio(1.5).build()
+    }
+
+    /// Returns the settings of a quiet 12x8 render with the given samples per pixel
+    fn settings(samples: u32) -> RenderSettings {
+        RenderSettings {
+            stop: StopCondition::Samples(samples),
+            max_depth: 8,
+            observer: None,
+            ..RenderSettings::new(12, 8)
+        }
+    }
+
+    /// Returns the framebuffer of the render of the sphere with the settings
+    fn render_spheres(settings: &RenderSettings) -> Framebuffer {
+        render(settings, &camera(), &world()).unwrap()
+    }
+
+    /// Returns every value the framebuffer keeps, to compare framebuffers bit for bit
+    fn samples(framebuffer: &Framebuffer) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        framebuffer.write_samples(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// Returns the path of a temporary file for the test
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("ray_tracer_{}_{}", process::id(), name))
+    }
+
+    #[test]
+    fn every_pixel_gets_the_samples_of_all_the_passes() {
+        // Passes of 1, 2, 4 and the 3 samples left
+        let framebuffer = render_spheres(&settings(10));
+        assert_eq!(framebuffer.sample_range(), (10, 10));
+        assert_eq!((framebuffer.width(), framebuffer.height()), (12, 8));
+    }
+
+    #[test]
+    fn the_same_seed_renders_the_same_image() {
+        let image = samples(&render_spheres(&settings(4)));
+        assert!(image == samples(&render_spheres(&settings(4))));
+        let other_seed = RenderSettings {
+            seed: 7,
+            ..settings(4)
+        };
+        assert!(image != samples(&render_spheres(&other_seed)));
+    }
+
+    #[test]
+    fn the_progressive_output_has_the_last_pass() {
+        let path = temp_path("progressive");
+        let ppm = path.with_extension("ppm");
+        let settings = RenderSettings {
+            progressive_output: Some(path.to_string_lossy().into_owned()),
+            ..settings(6)
+        };
+        let framebuffer = render_spheres(&settings);
+        let (width, height, pixels) = read_ppm(&ppm).unwrap();
+        fs::remove_file(&ppm).unwrap();
+
+        assert_eq!((width, height), (12, 8));
+        let written: Vec<u8> = pixels
+            .iter()
+            .flat_map(|c| [c.x, c.y, c.z])
+            .map(|c| (c * 255.0).round() as u8)
+            .collect();
+        assert_eq!(written, framebuffer.to_rgb8());
+    }
+
+    #[test]
+    fn passes_skip_the_pixels_that_are_done() {
+        let mut framebuffer = Framebuffer::new(4, 1);
+        let white = Color::new(1.0, 1.0, 1.0);
+        for (x, colors) in [(0, 8), (1, 4), (2, 4), (3, 1)] {
+            for n in 0..colors {
+                // The third pixel alternates between black and white, the others are flat
+                let color = if x == 2 && n % 2 == 1 {
+                    Color::zero()
+                } else {
+                    white
+                };
+                framebuffer.add_sample(x, 0, (0.0, 0.0), color);
+            }
+        }
+        let adaptive = AdaptiveSampling {
+            min_samples: 4,
+            max_samples: 8,
+            noise_threshold: 0.1,
+        };
+        // The first pixel has the most samples, the second one is flat after the fewest and
+        // the noisy one goes on, without going over the most samples
+        assert_eq!(plan_pass(&framebuffer, &adaptive, 7), [0, 0, 3, 6]);
+        assert_eq!(plan_pass(&framebuffer, &adaptive, 16), [0, 0, 4, 7]);
+    }
+
+    #[test]
+    fn adaptive_renders_sample_the_noisy_pixels_more() {
+        let adaptive = AdaptiveSampling {
+            min_samples: 7,
+            max_samples: 64,
+            noise_threshold: 0.02,
+        };
+        let settings = RenderSettings {
+            adaptive: Some(adaptive),
+            ..settings(64)
+        };
+        let framebuffer = render_spheres(&settings);
+        // The sky is flat so its pixels stop after the passes of 1, 2 and 4 samples, the lit
+        // spheres are noisy
+        assert_eq!(framebuffer.sample_range(), (7, 64));
+        assert_eq!(framebuffer.samples(0, 0), 7);
+        assert!(framebuffer.samples(6, 5) > 7);
+        assert!(framebuffer.average_samples() < 64.0);
+    }
+
+    #[test]
+    fn timed_renders_finish_their_passes_in_time() {
+        let settings = RenderSettings {
+            stop: StopCondition::Time(Duration::from_millis(300)),
+            ..settings(0)
+        };
+        let start = Instant::now();
+        let framebuffer = render_spheres(&settings);
+        assert!(
+            start.elapsed() < Duration::from_secs(3),
+            "{:?}",
+            start.elapsed()
+        );
+        let (fewest, most) = framebuffer.sample_range();
+        assert!(fewest > 0);
+        assert_eq!(fewest, most);
+    }
+
+    #[test]
+    fn noise_targets_stop_once_the_noise_is_trusted() {
+        // Any noise is under the threshold, so the render stops at the first pass whose
+        // pixels all have 16 samples, the passes bring them to 1, 3, 7, 15 and 31
+        let reachable = RenderSettings {
+            stop: StopCondition::Noise {
+                threshold: 100.0,
+                max_samples: 100,
+            },
+            ..settings(0)
+        };
+        assert_eq!(render_spheres(&reachable).sample_range(), (31, 31));
+
+        // A threshold that can't be reached stops at the most samples
+        let unreachable = RenderSettings {
+            stop: StopCondition::Noise {
+                threshold: 0.0,
+                max_samples: 20,
+            },
+            ..settings(0)
+        };
+        assert_eq!(render_spheres(&unreachable).sample_range(), (20, 20));
+    }
+
+    #[test]
+    fn cancelled_renders_return_what_they_have() {
+        let token = CancellationToken::new();
+        token.cancel();
+        let finished = Rc::new(Cell::new(false));
+        let observer = Rc::new(Finished(finished.clone()));
+        let cancelled = RenderSettings {
+            cancellation: Some(token),
+            observer: Some(observer),
+            ..settings(4)
+        };
+        let framebuffer = render_spheres(&cancelled);
+        assert_eq!(framebuffer.total_samples(), 0);
+        assert!(finished.get());
+
+        // A render cancelled by its observer stops at the end of the tile in progress
+        let token = CancellationToken::new();
+        let cancel = token.clone();
+        let settings = RenderSettings {
+            image_width: 96,
+            image_height: 64,
+            cancellation: Some(token),
+            observer: Some(Rc::new(move |_: &Progress| cancel.cancel())),
+            ..settings(4)
+        };
+        let framebuffer = render_spheres(&settings);
+        // The first tile got the single sample of the first pass
+        assert_eq!(framebuffer.total_samples(), (TILE_SIZE * TILE_SIZE) as u64);
+    }
+
+    #[test]
+    fn crop_windows_render_the_pixels_their_samples_come_from() {
+        let crop = CropWindow::new(30, 20, 40, 20);
+        let windows: Vec<CropWindow> = tiles(crop).iter().map(|tile| tile.window).collect();
+        // The tiles are the parts of the tiles of the full image inside the window
+        assert_eq!(
+            windows,
+            [
+                CropWindow::new(30, 20, 2, 12),
+                CropWindow::new(32, 20, 32, 12),
+                CropWindow::new(64, 20, 6, 12),
+                CropWindow::new(30, 32, 2, 8),
+                CropWindow::new(32, 32, 32, 8),
+                CropWindow::new(64, 32, 6, 8),
+            ]
+        );
+
+        let settings = RenderSettings::new(72, 40);
+        assert_eq!(render_region(&settings, crop), crop);
+        let filtered = RenderSettings {
+            filter: Filter::new(FilterKind::Gaussian, 1.5).unwrap(),
+            ..settings
+        };
+        // The filter reaches a pixel around the window, which is cut at the bottom of the image
+        assert_eq!(
+            render_region(&filtered, crop),
+            CropWindow::new(29, 19, 42, 21)
+        );
+    }
+
+    #[test]
+    fn crop_windows_look_like_the_full_image() {
+        let full = render_spheres(&settings(2));
+        let crop = CropWindow::new(3, 2, 5, 4);
+        let cropped = render_spheres(&RenderSettings {
+            crop: Some(crop),
+            ..settings(2)
+        });
+        assert!(samples(&cropped) == samples(&full.crop(3, 2, 5, 4)));
+
+        let outside = RenderSettings {
+            crop: Some(CropWindow::new(20, 2, 5, 4)),
+            ..settings(2)
+        };
+        let error = render(&outside, &camera(), &world()).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
 }
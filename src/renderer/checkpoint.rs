@@ -0,0 +1,222 @@
+use crate::framebuffer::{read_u32, Framebuffer};
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+/// Bytes at the start of every checkpoint file
+const MAGIC: &[u8; 8] = b"RTCKPT03";
+
+/// Longest description of the render settings a checkpoint file can have
+const MAX_SETTINGS_LENGTH: u32 = 4096;
+
+/// Represents the file where a render periodically saves its samples so it can be resumed
+/// after being interrupted
+#[derive(Clone)]
+pub struct Checkpoint {
+    /// Path of the checkpoint file, if it exists when the render starts the render resumes
+    /// from it
+    pub path: PathBuf,
+    /// Least time between two saves, the samples are saved after the pass in which it passes
+    pub interval: Duration,
+}
+
+/// Represents the state of a render saved in a checkpoint
+pub(crate) struct SavedRender {
+    /// Samples per pixel every pixel that isn't done has after the last finished pass
+    pub reached: u32,
+    /// Samples per pixel of the next pass
+    pub pass_samples: u32,
+    /// Samples accumulated so far
+    pub framebuffer: Framebuffer,
+}
+
+impl Checkpoint {
+    /// Returns a checkpoint saved to the given file at most once every `interval`
+    ///
+    /// # Arguments
+    ///
+    /// * path - Path of the checkpoint file
+    /// * interval - Least time between two saves
+    pub fn new(path: impl Into<PathBuf>, interval: Duration) -> Self {
+        Self {
+            path: path.into(),
+            interval,
+        }
+    }
+
+    /// Writes the state of the render to the checkpoint file, through a temporary file so an
+    /// interruption while saving doesn't lose the previous checkpoint
+    ///
+    /// # Arguments
+    ///
+    /// * settings - Description of the settings of the render that change its samples
+    /// * reached - Samples per pixel every pixel that isn't done has after the last finished
+    ///   pass
+    /// * pass_samples - Samples per pixel of the next pass
+    /// * framebuffer - Samples accumulated so far
+    pub(crate) fn save(
+        &self,
+        settings: &str,
+        reached: u32,
+        pass_samples: u32,
+        framebuffer: &Framebuffer,
+    ) -> io::Result<()> {
+        let temporary = self.path.with_extension("tmp");
+        {
+            let mut writer = BufWriter::new(File::create(&temporary)?);
+            writer.write_all(MAGIC)?;
+            writer.write_all(&(settings.len() as u32).to_le_bytes())?;
+            writer.write_all(settings.as_bytes())?;
+            writer.write_all(&reached.to_le_bytes())?;
+            writer.write_all(&pass_samples.to_le_bytes())?;
+            framebuffer.write_samples(&mut writer)?;
+            writer.flush()?;
+        }
+        fs::rename(&temporary, &self.path)
+    }
+
+    /// Reads the state of the render from the checkpoint file, None if the file doesn't exist
+    ///
+    /// The settings the checkpoint was saved with are compared before its samples are read, a
+    /// checkpoint of a render with other settings is an `InvalidInput` error.
+    ///
+    /// # Arguments
+    ///
+    /// * settings - Description of the settings of the render that change its samples
+    /// * width - The width in pixels of the framebuffer of the render
+    /// * height - The height in pixels of the framebuffer of the render
+    pub(crate) fn load(
+        &self,
+        settings: &str,
+        width: usize,
+        height: usize,
+    ) -> io::Result<Option<SavedRender>> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let mut reader = BufReader::new(file);
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a checkpoint file",
+            ));
+        }
+        let length = read_u32(&mut reader)?;
+        if length > MAX_SETTINGS_LENGTH {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the settings of the checkpoint are too long",
+            ));
+        }
+        let mut saved = vec![0; length as usize];
+        reader.read_exact(&mut saved)?;
+        let saved =
+            String::from_utf8(saved).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if saved != settings {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "it was saved by a render with other settings ({}) than this one ({})",
+                    saved, settings
+                ),
+            ));
+        }
+
+        Ok(Some(SavedRender {
+            reached: read_u32(&mut reader)?,
+            pass_samples: read_u32(&mut reader)?,
+            framebuffer: Framebuffer::read_samples(&mut reader, width, height)?,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use std::{env, process};
+
+    /// Returns a checkpoint in a temporary file for the test, without a file yet
+    fn checkpoint(name: &str) -> Checkpoint {
+        let path = env::temp_dir().join(format!("ray_tracer_{}_{}.ckpt", process::id(), name));
+        let _ = fs::remove_file(&path);
+        Checkpoint::new(path, Duration::from_secs(60))
+    }
+
+    /// Returns the error of loading the checkpoint
+    fn load_error(checkpoint: &Checkpoint, settings: &str) -> io::Error {
+        match checkpoint.load(settings, 2, 1) {
+            Ok(_) => panic!("the checkpoint was loaded"),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn saved_renders_are_loaded_back() {
+        let checkpoint = checkpoint("round_trip");
+        assert!(checkpoint.load("settings", 2, 1).unwrap().is_none());
+
+        let mut framebuffer = Framebuffer::new(2, 1);
+        framebuffer.add_sample(1, 0, (0.25, 0.0), Color::new(0.1, 0.2, 0.3));
+        checkpoint.save("settings", 7, 8, &framebuffer).unwrap();
+        assert!(!checkpoint.path.with_extension("tmp").exists());
+
+        let saved = checkpoint.load("settings", 2, 1).unwrap().unwrap();
+        fs::remove_file(&checkpoint.path).unwrap();
+        assert_eq!((saved.reached, saved.pass_samples), (7, 8));
+        assert_eq!(saved.framebuffer.samples(1, 0), 1);
+        assert_eq!(saved.framebuffer.color(1, 0), Color::new(0.1, 0.2, 0.3));
+    }
+
+    #[test]
+    fn checkpoints_of_other_renders_are_refused() {
+        let checkpoint = checkpoint("other_settings");
+        checkpoint
+            .save("seed 0", 1, 2, &Framebuffer::new(2, 1))
+            .unwrap();
+        let error = load_error(&checkpoint, "seed 1");
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(error
+            .to_string()
+            .contains("(seed 0) than this one (seed 1)"));
+
+        // A framebuffer of another size is found once the settings match
+        let error = match checkpoint.load("seed 0", 1, 2) {
+            Ok(_) => panic!("the checkpoint was loaded"),
+            Err(e) => e,
+        };
+        fs::remove_file(&checkpoint.path).unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn other_files_are_refused() {
+        let checkpoint = checkpoint("not_a_checkpoint");
+        fs::write(&checkpoint.path, b"P3\n2 1\n255\n").unwrap();
+        assert_eq!(
+            load_error(&checkpoint, "settings").to_string(),
+            "not a checkpoint file"
+        );
+
+        let mut too_long = MAGIC.to_vec();
+        too_long.extend_from_slice(&u32::MAX.to_le_bytes());
+        fs::write(&checkpoint.path, &too_long).unwrap();
+        assert_eq!(
+            load_error(&checkpoint, "settings").kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        fs::write(&checkpoint.path, &MAGIC[..4]).unwrap();
+        let error = load_error(&checkpoint, "settings");
+        fs::remove_file(&checkpoint.path).unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{
+    color::{read_ppm, write_ppm},
+    framebuffer::Framebuffer,
+};
+use std::{io, path::Path};
+
+/// Represents a rectangle of the image, in pixels of the full image, that is rendered on its
+/// own to re-render a problem area
+///
+/// The camera frames the full image, so the region looks exactly like in a render of the whole
+/// image with the same seed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CropWindow {
+    /// Column of the left edge, from the left of the image
+    pub x: usize,
+    /// Row of the top edge, from the top of the image
+    pub y: usize,
+    /// Width of the region in pixels
+    pub width: usize,
+    /// Height of the region in pixels
+    pub height: usize,
+}
+
+impl CropWindow {
+    /// Returns the region with the given top left corner and size
+    ///
+    /// # Arguments
+    ///
+    /// * x - Column of the left edge, from the left of the image
+    /// * y - Row of the top edge, from the top of the image
+    /// * width - Width of the region in pixels
+    /// * height - Height of the region in pixels
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns whether the region has no pixels
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Returns the part of the region that is inside an image of the given size, which is
+    /// empty if the region is outside of the image
+    pub fn clip(&self, image_width: usize, image_height: usize) -> Self {
+        let x = self.x.min(image_width);
+        let y = self.y.min(image_height);
+        Self {
+            x,
+            y,
+            width: self.width.min(image_width - x),
+            height: self.height.min(image_height - y),
+        }
+    }
+
+    /// Returns the part of the region that is inside an image of the given size, or an
+    /// `InvalidInput` error if it has no pixels
+    pub fn try_clip(&self, image_width: usize, image_height: usize) -> io::Result<Self> {
+        let clipped = self.clip(image_width, image_height);
+        if clipped.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "the {}x{} region at {},{} has no pixels inside the {}x{} image",
+                    self.width, self.height, self.x, self.y, image_width, image_height
+                ),
+            ));
+        }
+        Ok(clipped)
+    }
+
+    /// Writes a ppm file with the pixels of an existing image, replacing the region with the
+    /// framebuffer of its render
+    ///
+    /// # Arguments
+    ///
+    /// * framebuffer - The render of the region
+    /// * base - Path of the ppm file with the full image, it can be the file that is written
+    /// * image_name - The name of the file to write, without the `.ppm` extension
+    pub fn composite(
+        &self,
+        framebuffer: &Framebuffer,
+        base: &Path,
+        image_name: &str,
+    ) -> io::Result<()> {
+        let (width, height, pixels) = read_ppm(base)?;
+        if framebuffer.width() == 0 || framebuffer.height() == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the region has no pixels",
+            ));
+        }
+        if self.x + framebuffer.width() > width || self.y + framebuffer.height() > height {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the region is outside of the image",
+            ));
+        }
+
+        let mut rgb: Vec<u8> = pixels
+            .iter()
+            .flat_map(|c| [c.x, c.y, c.z])
+            .map(|c| (c * 255.0).round() as u8)
+            .collect();
+        let region = framebuffer.to_rgb8();
+        let row = framebuffer.width() * 3;
+        for (y, region_row) in region.chunks(row).enumerate() {
+            let start = ((self.y + y) * width + self.x) * 3;
+            rgb[start..start + row].copy_from_slice(region_row);
+        }
+
+        write_ppm(image_name, width, height, &rgb)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use std::{env, fs, process};
+
+    #[test]
+    fn regions_are_clipped_to_the_image() {
+        let inside = CropWindow::new(2, 1, 3, 2);
+        assert_eq!(inside.clip(8, 6), inside);
+        assert_eq!(
+            CropWindow::new(6, 4, 5, 5).clip(8, 6),
+            CropWindow::new(6, 4, 2, 2)
+        );
+        let outside = CropWindow::new(9, 1, 3, 2).clip(8, 6);
+        assert_eq!(outside, CropWindow::new(8, 1, 0, 2));
+        assert!(outside.is_empty());
+        assert!(CropWindow::new(1, 1, 3, 0).is_empty());
+    }
+
+    #[test]
+    fn regions_without_pixels_are_refused() {
+        assert_eq!(
+            CropWindow::new(6, 4, 5, 5).try_clip(8, 6).unwrap(),
+            CropWindow::new(6, 4, 2, 2)
+        );
+        let error = CropWindow::new(9, 1, 3, 2).try_clip(8, 6).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(
+            error.to_string(),
+            "the 3x2 region at 9,1 has no pixels inside the 8x6 image"
+        );
+    }
+
+    #[test]
+    fn regions_are_written_over_the_image() {
+        let name = env::temp_dir()
+            .join(format!("ray_tracer_{}_composite", process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let path = format!("{}.ppm", name);
+        write_ppm(&name, 4, 3, &[64; 4 * 3 * 3]).unwrap();
+
+        let mut region = Framebuffer::new(2, 1);
+        region.add_sample(0, 0, (0.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        let window = CropWindow::new(1, 1, 2, 1);
+        window.composite(&region, Path::new(&path), &name).unwrap();
+        let (width, height, pixels) = read_ppm(Path::new(&path)).unwrap();
+        let outside = CropWindow::new(3, 2, 2, 1).composite(&region, Path::new(&path), &name);
+        let empty = window.composite(&Framebuffer::new(0, 1), Path::new(&path), &name);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!((width, height), (4, 3));
+        let gray = Color::new(64.0, 64.0, 64.0) / 255.0;
+        for (i, pixel) in pixels.into_iter().enumerate() {
+            let expected = match i {
+                5 => Color::new(1.0, 1.0, 1.0),
+                6 => Color::zero(),
+                _ => gray,
+            };
+            assert_eq!(pixel, expected, "pixel {}", i);
+        }
+        assert_eq!(outside.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(empty.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,381 @@
+use std::{
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// Least time between two progress reports, so observers aren't called for every tile
+const REPORT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Represents how far a render has got
+#[derive(Clone, Copy, Debug)]
+pub struct Progress {
+    /// Fraction of the render that is done, between 0 and 1
+    pub fraction: f64,
+    /// Time since the render started
+    pub elapsed: Duration,
+    /// Estimated time until the render ends, None while nothing is done
+    pub eta: Option<Duration>,
+    /// Samples cast per second since the render started
+    pub samples_per_second: f64,
+}
+
+/// Receives the progress of a render
+///
+/// Closures that take a `&Progress` are observers too.
+pub trait ProgressObserver {
+    /// Called during the render, at most a few times per second
+    fn progress(&self, progress: &Progress);
+
+    /// Called once when the render ends, either because it's done or because it was cancelled
+    fn finished(&self, progress: &Progress) {
+        self.progress(progress);
+    }
+}
+
+impl<F: Fn(&Progress)> ProgressObserver for F {
+    fn progress(&self, progress: &Progress) {
+        self(progress)
+    }
+}
+
+/// Prints the progress to the standard error, rewriting the same line
+pub struct ConsoleProgress;
+
+impl ConsoleProgress {
+    /// Returns the line that describes the progress
+    fn line(progress: &Progress) -> String {
+        let eta = match progress.eta {
+            Some(eta) => format_duration(eta),
+            None => String::from("--:--"),
+        };
+        format!(
+            "{:5.1}% done, {} elapsed, {} left, {:.0} samples/s",
+            progress.fraction * 100.0,
+            format_duration(progress.elapsed),
+            eta,
+            progress.samples_per_second
+        )
+    }
+}
+
+impl ProgressObserver for ConsoleProgress {
+    fn progress(&self, progress: &Progress) {
+        eprint!("\r{}", ConsoleProgress::line(progress));
+    }
+
+    fn finished(&self, progress: &Progress) {
+        eprintln!("\r{}", ConsoleProgress::line(progress));
+    }
+}
+
+/// Returns the duration as minutes and seconds, with the hours in front when there are any
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// Represents a request to stop a render, which can be made from another thread
+///
+/// The clones of a token share the request, so one is given to the render and another is kept
+/// to cancel it. The render stops at the end of the tile in progress and returns the samples
+/// it has cast so far.
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    /// Whether the render was asked to stop
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Returns a token that wasn't cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks the renders that have the token to stop
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether the token was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Represents what a render has to do to end, used to estimate the fraction that is done
+pub(crate) enum Goal {
+    /// Reach a total number of samples
+    Samples { done: u64, target: u64 },
+    /// Render for a time
+    Time(Duration),
+    /// Reach a noise, the fraction is estimated at the end of each pass
+    Noise(f64),
+}
+
+/// Keeps track of the progress of a render and reports it to an observer
+pub(crate) struct ProgressTracker {
+    /// Receives the reports
+    observer: Option<Rc<dyn ProgressObserver>>,
+    /// What the render has to do to end
+    goal: Goal,
+    /// Moment in which the render started
+    start: Instant,
+    /// Moment of the last report, None before the first one
+    last_report: Option<Instant>,
+    /// Samples cast since the render started
+    samples: u64,
+}
+
+impl ProgressTracker {
+    /// Returns a tracker for a render that starts now
+    ///
+    /// # Arguments
+    ///
+    /// * observer - Receives the reports
+    /// * goal - What the render has to do to end
+    pub fn new(observer: Option<Rc<dyn ProgressObserver>>, goal: Goal) -> Self {
+        let start = Instant::now();
+        Self {
+            observer,
+            goal,
+            start,
+            last_report: None,
+            samples: 0,
+        }
+    }
+
+    /// Changes what the render has to do to end
+    pub fn set_goal(&mut self, goal: Goal) {
+        self.goal = goal;
+    }
+
+    /// Counts samples that were cast and reports the progress if it's time to, the first
+    /// report is made right away
+    pub fn add_samples(&mut self, samples: u64) {
+        self.samples += samples;
+        if let Goal::Samples { done, .. } = &mut self.goal {
+            *done += samples;
+        }
+        if self
+            .last_report
+            .is_none_or(|last| last.elapsed() >= REPORT_INTERVAL)
+        {
+            self.last_report = Some(Instant::now());
+            if let Some(observer) = &self.observer {
+                observer.progress(&self.progress());
+            }
+        }
+    }
+
+    /// Reports the final progress of the render
+    pub fn finish(&self) {
+        if let Some(observer) = &self.observer {
+            observer.finished(&self.progress());
+        }
+    }
+
+    /// Returns the current progress of the render
+    fn progress(&self) -> Progress {
+        let elapsed = self.start.elapsed();
+        let fraction = match self.goal {
+            Goal::Samples { done, target } => done as f64 / target.max(1) as f64,
+            Goal::Time(duration) => elapsed.as_secs_f64() / duration.as_secs_f64().max(1e-9),
+            Goal::Noise(fraction) => fraction,
+        }
+        .clamp(0.0, 1.0);
+        let eta = if fraction > 0.0 {
+            Some(elapsed.mul_f64((1.0 - fraction) / fraction))
+        } else {
+            None
+        };
+
+        Progress {
+            fraction,
+            elapsed,
+            eta,
+            samples_per_second: self.samples as f64 / elapsed.as_secs_f64().max(1e-9),
+        }
+    }
+}
+
+/// Reports the progress of each frame of a sequence as the progress of the whole sequence
+pub(crate) struct SequenceProgress {
+    /// Receives the reports of the sequence
+    observer: Rc<dyn ProgressObserver>,
+    /// Moment in which the sequence started
+    start: Instant,
+    /// Index of the frame being rendered, from 0
+    frame: usize,
+    /// Number of frames of the sequence
+    frames: usize,
+}
+
+impl SequenceProgress {
+    /// Returns the observer of one frame of the sequence
+    ///
+    /// # Arguments
+    ///
+    /// * observer - Receives the reports of the sequence
+    /// * start - Moment in which the sequence started
+    /// * frame - Index of the frame, from 0
+    /// * frames - Number of frames of the sequence
+    pub(crate) fn new(
+        observer: Rc<dyn ProgressObserver>,
+        start: Instant,
+        frame: usize,
+        frames: usize,
+    ) -> Self {
+        Self {
+            observer,
+            start,
+            frame,
+            frames,
+        }
+    }
+
+    /// Returns the progress of the sequence given the progress of the frame
+    fn sequence_progress(&self, progress: &Progress) -> Progress {
+        let fraction =
+            ((self.frame as f64 + progress.fraction) / self.frames.max(1) as f64).clamp(0.0, 1.0);
+        let elapsed = self.start.elapsed();
+        let eta = if fraction > 0.0 {
+            Some(elapsed.mul_f64((1.0 - fraction) / fraction))
+        } else {
+            None
+        };
+
+        Progress {
+            fraction,
+            elapsed,
+            eta,
+            samples_per_second: progress.samples_per_second,
+        }
+    }
+}
+
+impl ProgressObserver for SequenceProgress {
+    fn progress(&self, progress: &Progress) {
+        self.observer.progress(&self.sequence_progress(progress));
+    }
+
+    fn finished(&self, progress: &Progress) {
+        let progress = self.sequence_progress(progress);
+        if self.frame + 1 == self.frames {
+            self.observer.finished(&progress);
+        } else {
+            self.observer.progress(&progress);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Keeps the reports it gets, with whether they were the final one
+    #[derive(Default)]
+    struct Reports(RefCell<Vec<(bool, f64)>>);
+
+    impl ProgressObserver for Reports {
+        fn progress(&self, progress: &Progress) {
+            self.0.borrow_mut().push((false, progress.fraction));
+        }
+
+        fn finished(&self, progress: &Progress) {
+            self.0.borrow_mut().push((true, progress.fraction));
+        }
+    }
+
+    #[test]
+    fn durations_are_minutes_and_seconds() {
+        assert_eq!(format_duration(Duration::from_secs(0)), "00:00");
+        assert_eq!(format_duration(Duration::from_millis(65_900)), "01:05");
+        assert_eq!(format_duration(Duration::from_secs(3725)), "1:02:05");
+    }
+
+    #[test]
+    fn console_lines_have_the_progress() {
+        let progress = Progress {
+            fraction: 0.25,
+            elapsed: Duration::from_secs(30),
+            eta: Some(Duration::from_secs(90)),
+            samples_per_second: 1234.4,
+        };
+        assert_eq!(
+            ConsoleProgress::line(&progress),
+            " 25.0% done, 00:30 elapsed, 01:30 left, 1234 samples/s"
+        );
+        let starting = Progress {
+            eta: None,
+            ..progress
+        };
+        assert!(ConsoleProgress::line(&starting).contains("--:-- left"));
+    }
+
+    #[test]
+    fn clones_of_a_token_share_the_cancellation() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!clone.is_cancelled());
+        token.cancel();
+        assert!(clone.is_cancelled());
+    }
+
+    #[test]
+    fn trackers_report_the_fraction_of_the_goal() {
+        let reports = Rc::new(Reports::default());
+        let mut tracker = ProgressTracker::new(
+            Some(reports.clone()),
+            Goal::Samples {
+                done: 0,
+                target: 200,
+            },
+        );
+        assert_eq!(tracker.progress().eta, None);
+        tracker.add_samples(50);
+        // The next reports wait for the interval, the final one doesn't
+        tracker.add_samples(50);
+        tracker.finish();
+        assert_eq!(*reports.0.borrow(), [(false, 0.25), (true, 0.5)]);
+
+        tracker.set_goal(Goal::Noise(1.5));
+        assert_eq!(tracker.progress().fraction, 1.0);
+        tracker.set_goal(Goal::Time(Duration::from_secs(3600)));
+        assert!(tracker.progress().fraction < 0.01);
+        assert!(tracker.progress().samples_per_second > 0.0);
+    }
+
+    #[test]
+    fn frames_report_the_progress_of_the_sequence() {
+        let reports = Rc::new(Reports::default());
+        let frame = |frame| SequenceProgress::new(reports.clone(), Instant::now(), frame, 4);
+        let progress = |fraction| Progress {
+            fraction,
+            elapsed: Duration::default(),
+            eta: None,
+            samples_per_second: 0.0,
+        };
+        frame(1).progress(&progress(0.5));
+        frame(1).finished(&progress(1.0));
+        frame(3).finished(&progress(1.0));
+        // Only the end of the last frame is the end of the sequence
+        assert_eq!(
+            *reports.0.borrow(),
+            [(false, 0.375), (false, 0.5), (true, 1.0)]
+        );
+    }
+}
@@ -0,0 +1,617 @@
+//! A text format that describes a scene, its camera and its render settings, so scenes can be
+//! stored in files or sent to other processes without recompiling
+//!
+//! Each line is a statement and `#` starts a comment:
+//!
+//! ```text
+//! image 600 337
+//! samples 100
+//! max_depth 50
+//! seed 0
+//! filter gaussian 1.5
+//! camera lookfrom 13 2 3 lookat 0 0 0 vup 0 1 0 vfov 20 aperture 0.1 focus_dist 10
+//! material ground lambertian 0.5 0.5 0.5
+//! material steel metal 0.7 0.6 0.5 0.1
+//! material glass dielectric 1.5
+//! material paint principled 0.8 0.1 0.1 roughness 0.3 clearcoat 1
+//! sphere 0 -1000 0 1000 ground
+//! ```
+//!
+//! Materials must be declared before the spheres that use them. The camera settings that are
+//! left out keep the defaults of `PerspectiveCamera::builder`, and the principled parameters
+//! the defaults of `Principled::new`. The filter is one of `box`, `tent`, `gaussian`,
+//! `mitchell` and `blackman_harris`, its radius in pixels can be left out to use the usual one.
+
+use crate::{
+    camera::PerspectiveCamera,
+    color::Color,
+    filter::{Filter, FilterKind},
+    hittable::{HittableList, Sphere},
+    material::{Dielectric, Lambertian, Material, Metal, Principled},
+    renderer::{RenderSettings, StopCondition},
+    vec3::{Point3, Vec3},
+};
+use std::{fmt, fs, io, path::Path, rc::Rc, str::FromStr, str::SplitWhitespace};
+
+/// Represents a material of a scene
+#[derive(Clone, Copy)]
+pub enum MaterialDescription {
+    /// A diffuse material, see `Lambertian`
+    Lambertian { color: Color },
+    /// A reflective material, see `Metal`
+    Metal { color: Color, fuzz: f64 },
+    /// A glass like material, see `Dielectric`
+    Dielectric { ir: f64 },
+    /// A layered material with constant parameters, see `Principled`
+    Principled {
+        base_color: Color,
+        metallic: f64,
+        roughness: f64,
+        specular: f64,
+        transmission: f64,
+        clearcoat: f64,
+        sheen: f64,
+        ir: f64,
+    },
+}
+
+impl MaterialDescription {
+    /// Returns the material it describes
+    pub fn build(&self) -> Rc<dyn Material> {
+        match *self {
+            MaterialDescription::Lambertian { color } => Rc::new(Lambertian::new(color)),
+            MaterialDescription::Metal { color, fuzz } => Rc::new(Metal::new(color, fuzz)),
+            MaterialDescription::Dielectric { ir } => Rc::new(Dielectric::new(ir)),
+            MaterialDescription::Principled {
+                base_color,
+                metallic,
+                roughness,
+                specular,
+                transmission,
+                clearcoat,
+                sheen,
+                ir,
+            } => Rc::new(
+                Principled::new(base_color)
+                    .with_metallic(metallic)
+                    .with_roughness(roughness)
+                    .with_specular(specular)
+                    .with_transmission(transmission)
+                    .with_clearcoat(clearcoat)
+                    .with_sheen(sheen)
+                    .with_ir(ir),
+            ),
+        }
+    }
+}
+
+/// Represents the perspective camera of a scene, its aspect ratio is the image's
+#[derive(Clone, Copy)]
+pub struct CameraDescription {
+    /// Position of the camera
+    pub lookfrom: Point3,
+    /// Point where the camera is looking at
+    pub lookat: Point3,
+    /// Vector representing the up orientation
+    pub vup: Vec3,
+    /// Vertical field of view in degrees
+    pub vfov: f64,
+    /// Diameter of the lens' aperture
+    pub aperture: f64,
+    /// Distance to the plane in focus, if None the distance between lookfrom and lookat
+    pub focus_dist: Option<f64>,
+}
+
+impl Default for CameraDescription {
+    fn default() -> Self {
+        Self {
+            lookfrom: Point3::zero(),
+            lookat: Point3::new(0.0, 0.0, -1.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+            vfov: 90.0,
+            aperture: 0.0,
+            focus_dist: None,
+        }
+    }
+}
+
+/// Represents a sphere of a scene
+#[derive(Clone)]
+pub struct SphereDescription {
+    /// Center of the sphere
+    pub center: Point3,
+    /// Radius of the sphere
+    pub radius: f64,
+    /// Name of the sphere's material
+    pub material: String,
+}
+
+/// Represents a scene with its camera and its render settings
+#[derive(Clone)]
+pub struct Scene {
+    /// The width in pixels of the image
+    pub image_width: i32,
+    /// The height in pixels of the image
+    pub image_height: i32,
+    /// The number of rays that will be cast per pixel
+    pub samples_per_pixel: u32,
+    /// The maximum number of bounces a ray can have
+    pub max_depth: i32,
+    /// Seed of the random numbers of the render
+    pub seed: u64,
+    /// How the samples are weighted to reconstruct the pixels
+    pub filter: Filter,
+    /// The camera that will cast the rays
+    pub camera: CameraDescription,
+    /// The materials with their names, in the order they were declared
+    pub materials: Vec<(String, MaterialDescription)>,
+    /// The spheres of the scene
+    pub spheres: Vec<SphereDescription>,
+}
+
+impl Scene {
+    /// Returns an empty scene rendered at the given size with 100 samples per pixel, up to 50
+    /// bounces, the seed 0, a box filter and the default camera
+    ///
+    /// # Arguments
+    ///
+    /// * image_width - The width in pixels of the image
+    /// * image_height - The height in pixels of the image
+    pub fn new(image_width: i32, image_height: i32) -> Self {
+        Self {
+            image_width,
+            image_height,
+            samples_per_pixel: 100,
+            max_depth: 50,
+            seed: 0,
+            filter: Filter::default(),
+            camera: CameraDescription::default(),
+            materials: Vec::new(),
+            spheres: Vec::new(),
+        }
+    }
+
+    /// Reads a scene from a file
+    ///
+    /// # Arguments
+    ///
+    /// * path - Path of the scene file
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Scene::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Writes the scene to a file
+    ///
+    /// # Arguments
+    ///
+    /// * path - Path of the scene file
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    /// Returns the scene described by the text, or an `InvalidData` error with the number of
+    /// the line that couldn't be read
+    ///
+    /// # Arguments
+    ///
+    /// * text - The scene in the scene file format
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut scene = Scene::new(600, 337);
+
+        for (i, line) in text.lines().enumerate() {
+            let content = line.split('#').next().unwrap_or("");
+            let mut line = Line {
+                number: i + 1,
+                tokens: content.split_whitespace(),
+            };
+            let keyword = match line.tokens.next() {
+                Some(keyword) => keyword,
+                None => continue,
+            };
+
+            match keyword {
+                "image" => {
+                    scene.image_width = line.value()?;
+                    scene.image_height = line.value()?;
+                }
+                "samples" => scene.samples_per_pixel = line.value()?,
+                "max_depth" => scene.max_depth = line.value()?,
+                "seed" => scene.seed = line.value()?,
+                "filter" => scene.filter = line.filter()?,
+                "camera" => scene.camera = line.camera()?,
+                "material" => {
+                    let name = line.word()?.to_string();
+                    let material = line.material()?;
+                    match scene.materials.iter_mut().find(|(n, _)| *n == name) {
+                        Some(declared) => declared.1 = material,
+                        None => scene.materials.push((name, material)),
+                    }
+                }
+                "sphere" => {
+                    let center = line.vec3()?;
+                    let radius = line.value()?;
+                    let material = line.word()?.to_string();
+                    if !scene.materials.iter().any(|(name, _)| *name == material) {
+                        return Err(line.error(&format!("unknown material {}", material)));
+                    }
+                    scene.spheres.push(SphereDescription {
+                        center,
+                        radius,
+                        material,
+                    });
+                }
+                _ => return Err(line.error(&format!("unknown statement {}", keyword))),
+            }
+            line.end()?;
+        }
+
+        Ok(scene)
+    }
+
+    /// Returns the hittables of the scene, the spheres with the same material share it
+    pub fn world(&self) -> HittableList {
+        let materials: Vec<(&str, Rc<dyn Material>)> = self
+            .materials
+            .iter()
+            .map(|(name, material)| (name.as_str(), material.build()))
+            .collect();
+
+        let mut world = HittableList::new();
+        for sphere in &self.spheres {
+            if let Some((_, material)) = materials.iter().find(|(n, _)| *n == sphere.material) {
+                world.add(Sphere::with_shared_material(
+                    sphere.center,
+                    sphere.radius,
+                    material.clone(),
+                ));
+            }
+        }
+        world
+    }
+
+    /// Returns the camera of the scene, with the aspect ratio of the image
+    pub fn camera(&self) -> PerspectiveCamera {
+        let mut builder = PerspectiveCamera::builder()
+            .lookfrom(self.camera.lookfrom)
+            .lookat(self.camera.lookat)
+            .vup(self.camera.vup)
+            .vfov(self.camera.vfov)
+            .aspect_ratio(self.image_width as f64 / self.image_height as f64)
+            .aperture(self.camera.aperture);
+        if let Some(focus_dist) = self.camera.focus_dist {
+            builder = builder.focus_dist(focus_dist);
+        }
+        builder.build()
+    }
+
+    /// Returns the settings to render the scene, with the other settings at their defaults
+    pub fn settings(&self) -> RenderSettings {
+        RenderSettings {
+            stop: StopCondition::Samples(self.samples_per_pixel),
+            max_depth: self.max_depth,
+            seed: self.seed,
+            filter: self.filter,
+            ..RenderSettings::new(self.image_width, self.image_height)
+        }
+    }
+}
+
+impl fmt::Display for Scene {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "image {} {}", self.image_width, self.image_height)?;
+        writeln!(f, "samples {}", self.samples_per_pixel)?;
+        writeln!(f, "max_depth {}", self.max_depth)?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "filter {} {}", self.filter.kind(), self.filter.radius())?;
+
+        let camera = &self.camera;
+        write!(
+            f,
+            "camera lookfrom {} lookat {} vup {} vfov {} aperture {}",
+            camera.lookfrom, camera.lookat, camera.vup, camera.vfov, camera.aperture
+        )?;
+        match camera.focus_dist {
+            Some(focus_dist) => writeln!(f, " focus_dist {}", focus_dist)?,
+            None => writeln!(f)?,
+        }
+
+        for (name, material) in &self.materials {
+            match material {
+                MaterialDescription::Lambertian { color } => {
+                    writeln!(f, "material {} lambertian {}", name, color)?
+                }
+                MaterialDescription::Metal { color, fuzz } => {
+                    writeln!(f, "material {} metal {} {}", name, color, fuzz)?
+                }
+                MaterialDescription::Dielectric { ir } => {
+                    writeln!(f, "material {} dielectric {}", name, ir)?
+                }
+                MaterialDescription::Principled {
+                    base_color,
+                    metallic,
+                    roughness,
+                    specular,
+                    transmission,
+                    clearcoat,
+                    sheen,
+                    ir,
+                } => writeln!(
+                    f,
+                    "material {} principled {} metallic {} roughness {} specular {} \
+                     transmission {} clearcoat {} sheen {} ir {}",
+                    name,
+                    base_color,
+                    metallic,
+                    roughness,
+                    specular,
+                    transmission,
+                    clearcoat,
+                    sheen,
+                    ir
+                )?,
+            }
+        }
+
+        for sphere in &self.spheres {
+            writeln!(
+                f,
+                "sphere {} {} {}",
+                sphere.center, sphere.radius, sphere.material
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Represents the tokens of a line of a scene file that are still to be read
+struct Line<'a> {
+    /// Number of the line, from 1
+    number: usize,
+    /// The remaining tokens
+    tokens: SplitWhitespace<'a>,
+}
+
+impl<'a> Line<'a> {
+    /// Returns an `InvalidData` error with the number of the line
+    fn error(&self, message: &str) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("line {}: {}", self.number, message),
+        )
+    }
+
+    /// Reads the next token
+    fn word(&mut self) -> io::Result<&'a str> {
+        match self.tokens.next() {
+            Some(word) => Ok(word),
+            None => Err(self.error("missing value")),
+        }
+    }
+
+    /// Reads the next token as a number
+    fn value<T: FromStr>(&mut self) -> io::Result<T> {
+        let word = self.word()?;
+        word.parse()
+            .map_err(|_| self.error(&format!("invalid number {}", word)))
+    }
+
+    /// Reads the next three tokens as a vector
+    fn vec3(&mut self) -> io::Result<Vec3> {
+        Ok(Vec3::new(self.value()?, self.value()?, self.value()?))
+    }
+
+    /// Fails if there are tokens left
+    fn end(&mut self) -> io::Result<()> {
+        match self.tokens.next() {
+            Some(word) => Err(self.error(&format!("unexpected {}", word))),
+            None => Ok(()),
+        }
+    }
+
+    /// Reads the kind of a filter and its radius, if it's given
+    fn filter(&mut self) -> io::Result<Filter> {
+        let name = self.word()?;
+        let kind: FilterKind = name
+            .parse()
+            .map_err(|_| self.error(&format!("unknown filter {}", name)))?;
+        let radius = match self.tokens.clone().next() {
+            Some(_) => self.value()?,
+            None => kind.default_radius(),
+        };
+        Filter::new(kind, radius).map_err(|e| self.error(&e.to_string()))
+    }
+
+    /// Reads the named settings of a camera
+    fn camera(&mut self) -> io::Result<CameraDescription> {
+        let mut camera = CameraDescription::default();
+        while let Some(key) = self.tokens.next() {
+            match key {
+                "lookfrom" => camera.lookfrom = self.vec3()?,
+                "lookat" => camera.lookat = self.vec3()?,
+                "vup" => camera.vup = self.vec3()?,
+                "vfov" => camera.vfov = self.value()?,
+                "aperture" => camera.aperture = self.value()?,
+                "focus_dist" => camera.focus_dist = Some(self.value()?),
+                _ => return Err(self.error(&format!("unknown camera setting {}", key))),
+            }
+        }
+        Ok(camera)
+    }
+
+    /// Reads the kind and the parameters of a material
+    fn material(&mut self) -> io::Result<MaterialDescription> {
+        match self.word()? {
+            "lambertian" => Ok(MaterialDescription::Lambertian {
+                color: self.vec3()?,
+            }),
+            "metal" => Ok(MaterialDescription::Metal {
+                color: self.vec3()?,
+                fuzz: self.value()?,
+            }),
+            "dielectric" => Ok(MaterialDescription::Dielectric { ir: self.value()? }),
+            "principled" => {
+                let base_color = self.vec3()?;
+                let (mut metallic, mut roughness, mut specular) = (0.0, 0.5, 0.5);
+                let (mut transmission, mut clearcoat, mut sheen, mut ir) = (0.0, 0.0, 0.0, 1.5);
+                while let Some(key) = self.tokens.next() {
+                    let value = self.value()?;
+                    match key {
+                        "metallic" => metallic = value,
+                        "roughness" => roughness = value,
+                        "specular" => specular = value,
+                        "transmission" => transmission = value,
+                        "clearcoat" => clearcoat = value,
+                        "sheen" => sheen = value,
+                        "ir" => ir = value,
+                        _ => return Err(self.error(&format!("unknown parameter {}", key))),
+                    }
+                }
+                Ok(MaterialDescription::Principled {
+                    base_color,
+                    metallic,
+                    roughness,
+                    specular,
+                    transmission,
+                    clearcoat,
+                    sheen,
+                    ir,
+                })
+            }
+            kind => Err(self.error(&format!("unknown material kind {}", kind))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A scene with every kind of statement, comments and blank lines
+    const SCENE: &str = "\
+# A test scene
+image 64 36   # small
+samples 8
+max_depth 12
+seed 42
+filter mitchell
+camera lookfrom 13 2 3 lookat 0 0 0 vfov 20.5 aperture 0.1 focus_dist 10
+
+material ground lambertian 0.5 0.5 0.5
+material steel metal 0.7 0.6 0.5 0.125
+material glass dielectric 1.5
+material paint principled 0.8 0.1 0.1 roughness 0.3 clearcoat 1
+material steel metal 0.1 0.2 0.3 0
+sphere 0 -1000 0 1000 ground
+sphere -4 1 0 1 steel
+sphere 2 0.2 2 0.2 paint
+sphere 0 1 0 1 glass
+";
+
+    /// Returns the message of the error of parsing the text
+    fn error(text: &str) -> String {
+        match Scene::parse(text) {
+            Ok(_) => String::from("the scene was parsed"),
+            Err(e) => {
+                assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+                e.to_string()
+            }
+        }
+    }
+
+    #[test]
+    fn scenes_are_parsed() {
+        let scene = Scene::parse(SCENE).unwrap();
+        assert_eq!((scene.image_width, scene.image_height), (64, 36));
+        assert_eq!(
+            (scene.samples_per_pixel, scene.max_depth, scene.seed),
+            (8, 12, 42)
+        );
+        assert_eq!(scene.filter.kind(), FilterKind::Mitchell);
+        assert_eq!(scene.filter.radius(), FilterKind::Mitchell.default_radius());
+        assert_eq!(scene.camera.lookfrom, Point3::new(13.0, 2.0, 3.0));
+        assert_eq!(scene.camera.vup, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(scene.camera.vfov, 20.5);
+        assert_eq!(scene.camera.focus_dist, Some(10.0));
+
+        // A material declared again replaces the first one, in the same place
+        let names: Vec<&str> = scene.materials.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, ["ground", "steel", "glass", "paint"]);
+        match scene.materials[1].1 {
+            MaterialDescription::Metal { color, fuzz } => {
+                assert_eq!((color, fuzz), (Color::new(0.1, 0.2, 0.3), 0.0))
+            }
+            _ => panic!("steel isn't a metal"),
+        }
+        match scene.materials[3].1 {
+            MaterialDescription::Principled {
+                roughness,
+                clearcoat,
+                metallic,
+                ir,
+                ..
+            } => assert_eq!((roughness, clearcoat, metallic, ir), (0.3, 1.0, 0.0, 1.5)),
+            _ => panic!("paint isn't principled"),
+        }
+        assert_eq!(scene.spheres.len(), 4);
+        assert_eq!(scene.spheres[2].center, Point3::new(2.0, 0.2, 2.0));
+        assert_eq!(scene.spheres[2].material, "paint");
+    }
+
+    #[test]
+    fn written_scenes_are_read_back_the_same() {
+        let scene = Scene::parse(SCENE).unwrap();
+        let text = scene.to_string();
+        let read = Scene::parse(&text).unwrap();
+        assert_eq!(read.to_string(), text);
+        assert_eq!(read.filter.radius(), scene.filter.radius());
+        assert_eq!(read.camera.lookat, scene.camera.lookat);
+        assert_eq!(read.spheres[3].radius, 1.0);
+
+        // Values that don't have a short decimal form are kept exactly
+        let mut scene = Scene::new(10, 10);
+        scene.camera.vfov = 1.0 / 3.0;
+        scene.camera.focus_dist = None;
+        let read = Scene::parse(&scene.to_string()).unwrap();
+        assert_eq!(read.camera.vfov, 1.0 / 3.0);
+        assert_eq!(read.camera.focus_dist, None);
+    }
+
+    #[test]
+    fn errors_have_the_line_number() {
+        assert_eq!(error("image 10\n"), "line 1: missing value");
+        assert_eq!(error("\nimage 10 ten\n"), "line 2: invalid number ten");
+        assert_eq!(error("samples 4 4\n"), "line 1: unexpected 4");
+        assert_eq!(error("light 0 0 0\n"), "line 1: unknown statement light");
+        assert_eq!(
+            error("sphere 0 0 0 1 glass\n"),
+            "line 1: unknown material glass"
+        );
+        assert_eq!(error("filter sinc\n"), "line 1: unknown filter sinc");
+        assert_eq!(
+            error("camera zoom 2\n"),
+            "line 1: unknown camera setting zoom"
+        );
+        assert_eq!(
+            error("material a plastic 1\n"),
+            "line 1: unknown material kind plastic"
+        );
+        assert_eq!(
+            error("material a principled 1 1 1 gloss 1\n"),
+            "line 1: unknown parameter gloss"
+        );
+        assert!(error("filter box 0\n").starts_with("line 1: "));
+    }
+
+    #[test]
+    fn scenes_give_their_world_camera_and_settings() {
+        let scene = Scene::parse(SCENE).unwrap();
+        let settings = scene.settings();
+        assert_eq!((settings.image_width, settings.image_height), (64, 36));
+        assert!(matches!(settings.stop, StopCondition::Samples(8)));
+        assert_eq!((settings.max_depth, settings.seed), (12, 42));
+
+        let camera = scene.camera();
+        assert_eq!(camera.aspect_ratio(), 64.0 / 36.0);
+        assert_eq!(camera.focus_dist(), 10.0);
+    }
+}
@@ -0,0 +1,215 @@
+//! A small HTTP service that renders scenes sent in the scene file format
+//!
+//! * `POST /jobs` with a scene as the body queues a render job and answers its id
+//! * `GET /jobs` lists the jobs with their state and progress
+//! * `GET /jobs/{id}` answers the state and the progress of a job
+//! * `GET /jobs/{id}/image` answers the rendered image as a png file once the job is done
+//! * `DELETE /jobs/{id}` cancels a job
+//!
+//! The jobs are rendered one at a time in the order they were submitted, and the answers
+//! other than the image are JSON. At most 16 jobs wait to be rendered, a scene submitted while
+//! the queue is full gets a 503 error, and images have at most 4 megapixels. A job whose
+//! render fails has the status `failed` and the reason in its `error` field, and the jobs
+//! after it are still rendered. Jobs that are done, cancelled or failed are forgotten with
+//! their images an hour after they end, or earlier, starting with the ones that ended first,
+//! while more than 64 jobs have ended.
+
+mod http;
+mod jobs;
+
+pub use jobs::{JobQueue, JobStatus};
+
+use http::{read_request, Request, Response};
+use std::{
+    io::{self, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Most connections answered at the same time, the others are answered with a 503 error
+const MAX_CONNECTIONS: usize = 32;
+
+/// Longest time to wait for a client to send or receive part of a request or a response
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Represents a connection being answered, counted until it's dropped
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Answers the requests that reach the listener, each connection in its own thread, until
+/// the listener fails
+///
+/// At most `MAX_CONNECTIONS` connections are answered at the same time, and a client that
+/// sends or reads nothing for 30 seconds is disconnected.
+///
+/// # Arguments
+///
+/// * listener - Where the clients connect
+/// * jobs - The jobs of the service, `JobQueue::start` must have been called so they render
+pub fn serve(listener: &TcpListener, jobs: &JobQueue) -> io::Result<()> {
+    let connections = Arc::new(AtomicUsize::new(0));
+    loop {
+        let (stream, _) = listener.accept()?;
+        let slot = ConnectionSlot(connections.clone());
+        if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            // The short answer fits in the socket's buffer, a client that doesn't read it
+            // doesn't hold the accepting thread
+            let _ = stream.set_nonblocking(true);
+            let _ = Response::error(503, "too many connections").write_to(&mut &stream);
+            continue;
+        }
+        let jobs = jobs.clone();
+        thread::spawn(move || {
+            let _slot = slot;
+            if let Err(e) = handle_connection(stream, &jobs) {
+                eprintln!("couldn't answer a request: {}", e);
+            }
+        });
+    }
+}
+
+/// Reads a request from the connection and answers it, failing if the client sends or reads
+/// nothing for 30 seconds
+///
+/// # Arguments
+///
+/// * stream - The connection with the client
+/// * jobs - The jobs of the service
+pub fn handle_connection(stream: TcpStream, jobs: &JobQueue) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let response = match read_request(&mut BufReader::new(stream.try_clone()?)) {
+        Ok(request) => route(&request, jobs),
+        Err(e) => Response::error(400, &e.to_string()),
+    };
+    response.write_to(&mut &stream)
+}
+
+/// Returns the answer to a request
+fn route(request: &Request, jobs: &JobQueue) -> Response {
+    let segments: Vec<&str> = request.path.split('/').filter(|s| !s.is_empty()).collect();
+    let id = segments.get(1).and_then(|id| id.parse::<usize>().ok());
+
+    match (request.method.as_str(), &segments[..], id) {
+        ("POST", ["jobs"], _) => {
+            let text = String::from_utf8_lossy(&request.body);
+            match jobs.submit(&text) {
+                Ok(id) => Response::json(201, jobs.status_json(id).unwrap_or_default()),
+                Err(e) if e.kind() == io::ErrorKind::ResourceBusy => {
+                    Response::error(503, &e.to_string())
+                }
+                Err(e) => Response::error(400, &e.to_string()),
+            }
+        }
+        ("GET", ["jobs"], _) => Response::json(200, jobs.list_json()),
+        ("GET", ["jobs", _], Some(id)) => match jobs.status_json(id) {
+            Some(json) => Response::json(200, json),
+            None => Response::error(404, "unknown job"),
+        },
+        ("GET", ["jobs", _, "image"], Some(id)) => match jobs.image(id) {
+            Some((_, Some(png))) => Response::png(png),
+            Some((status, None)) => Response::error(409, &format!("the job is {}", status.name())),
+            None => Response::error(404, "unknown job"),
+        },
+        ("DELETE", ["jobs", _], Some(id)) => {
+            if jobs.cancel(id) {
+                Response::json(200, jobs.status_json(id).unwrap_or_default())
+            } else {
+                Response::error(404, "unknown job")
+            }
+        }
+        (_, ["jobs"], _) | (_, ["jobs", _], Some(_)) | (_, ["jobs", _, "image"], Some(_)) => {
+            Response::error(405, "method not allowed")
+        }
+        _ => Response::error(404, "not found"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers a request to the jobs, which aren't rendered since the queue isn't started, and
+    /// returns the status code and the body of the answer
+    fn answer(jobs: &JobQueue, method: &str, path: &str, body: &str) -> (u16, String) {
+        let request = Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            body: body.as_bytes().to_vec(),
+        };
+        let mut written = Vec::new();
+        route(&request, jobs).write_to(&mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        let status = written[9..12].parse().unwrap();
+        let body = written.split("\r\n\r\n").nth(1).unwrap_or("").to_string();
+        (status, body)
+    }
+
+    #[test]
+    fn jobs_are_submitted_queried_and_cancelled() {
+        let jobs = JobQueue::new();
+        let (status, body) = answer(&jobs, "POST", "/jobs", "image 4 4\n");
+        assert_eq!(status, 201);
+        assert!(
+            body.starts_with("{\"id\":0,\"status\":\"queued\""),
+            "{}",
+            body
+        );
+
+        assert_eq!(answer(&jobs, "GET", "/jobs/0", "").0, 200);
+        assert_eq!(answer(&jobs, "GET", "/jobs", "").0, 200);
+        let (status, body) = answer(&jobs, "GET", "/jobs/0/image", "");
+        assert_eq!(
+            (status, body.as_str()),
+            (409, "{\"error\":\"the job is queued\"}")
+        );
+
+        let (status, body) = answer(&jobs, "DELETE", "/jobs/0", "");
+        assert_eq!(status, 200);
+        assert!(body.contains("\"status\":\"cancelled\""), "{}", body);
+    }
+
+    #[test]
+    fn unknown_paths_and_methods_are_refused() {
+        let jobs = JobQueue::new();
+        assert_eq!(answer(&jobs, "GET", "/jobs/7", "").0, 404);
+        assert_eq!(answer(&jobs, "DELETE", "/jobs/7", "").0, 404);
+        assert_eq!(answer(&jobs, "GET", "/jobs/7/image", "").0, 404);
+        assert_eq!(answer(&jobs, "GET", "/scenes", "").0, 404);
+        assert_eq!(answer(&jobs, "PUT", "/jobs", "").0, 405);
+        assert_eq!(answer(&jobs, "POST", "/jobs/0", "").0, 405);
+    }
+
+    #[test]
+    fn invalid_scenes_are_refused() {
+        let jobs = JobQueue::new();
+        assert_eq!(answer(&jobs, "POST", "/jobs", "image four 4\n").0, 400);
+        assert_eq!(answer(&jobs, "POST", "/jobs", "image 1 4\n").0, 400);
+        assert_eq!(answer(&jobs, "POST", "/jobs", "image 4096 4096\n").0, 400);
+        assert_eq!(answer(&jobs, "GET", "/jobs", "").1, "[]");
+    }
+
+    #[test]
+    fn scenes_are_refused_before_parsing_while_the_queue_is_full() {
+        let jobs = JobQueue::new();
+        for _ in 0..16 {
+            assert_eq!(answer(&jobs, "POST", "/jobs", "image 4 4\n").0, 201);
+        }
+        assert_eq!(answer(&jobs, "POST", "/jobs", "image 4 4\n").0, 503);
+        assert_eq!(answer(&jobs, "POST", "/jobs", "image four 4\n").0, 503);
+
+        // A cancelled job leaves room in the queue
+        assert_eq!(answer(&jobs, "DELETE", "/jobs/0", "").0, 200);
+        assert_eq!(answer(&jobs, "POST", "/jobs", "image 4 4\n").0, 201);
+    }
+}
@@ -0,0 +1,247 @@
+use std::io::{self, BufRead, Read, Write};
+
+/// Longest request body accepted, in bytes
+const MAX_BODY: usize = 16 << 20;
+
+/// Most header lines accepted in a request
+const MAX_HEADERS: usize = 100;
+
+/// Represents an HTTP request
+pub(crate) struct Request {
+    /// Method of the request, like `GET`
+    pub method: String,
+    /// Path of the request, without the query
+    pub path: String,
+    /// Body of the request, empty without a `Content-Length` header
+    pub body: Vec<u8>,
+}
+
+/// Represents an HTTP response
+pub(crate) struct Response {
+    /// Status code
+    status: u16,
+    /// Media type of the body
+    content_type: &'static str,
+    /// Body of the response
+    body: Vec<u8>,
+}
+
+/// Reads an HTTP/1.x request
+///
+/// # Arguments
+///
+/// * reader - The connection with the client
+pub(crate) fn read_request(reader: &mut impl BufRead) -> io::Result<Request> {
+    let request_line = read_line(reader)?;
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method.to_string(), target),
+        _ => return Err(invalid("invalid request line")),
+    };
+    let path = target.split('?').next().unwrap_or("").to_string();
+
+    let mut content_length = 0;
+    for _ in 0..MAX_HEADERS {
+        let line = read_line(reader)?;
+        if line.is_empty() {
+            // The body grows as it arrives, so a length that isn't sent isn't allocated
+            let mut body = Vec::new();
+            reader.take(content_length as u64).read_to_end(&mut body)?;
+            if body.len() < content_length {
+                return Err(invalid("incomplete body"));
+            }
+            return Ok(Request { method, path, body });
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid("invalid Content-Length"))?;
+                if content_length > MAX_BODY {
+                    return Err(invalid("the body is too long"));
+                }
+            }
+        }
+    }
+    Err(invalid("too many headers"))
+}
+
+/// Reads a line without its line break
+fn read_line(reader: &mut impl BufRead) -> io::Result<String> {
+    let mut line = String::new();
+    reader.take(8192).read_line(&mut line)?;
+    if !line.ends_with('\n') {
+        return Err(invalid("incomplete request"));
+    }
+    Ok(line.trim_end().to_string())
+}
+
+/// Returns an `InvalidData` error with the message
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+impl Response {
+    /// Returns a response with a JSON body
+    pub fn json(status: u16, body: String) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: body.into_bytes(),
+        }
+    }
+
+    /// Returns a response with a JSON body that has the error message
+    pub fn error(status: u16, message: &str) -> Self {
+        Response::json(status, format!("{{\"error\":{}}}", json_string(message)))
+    }
+
+    /// Returns a successful response with a png image
+    pub fn png(body: Vec<u8>) -> Self {
+        Self {
+            status: 200,
+            content_type: "image/png",
+            body,
+        }
+    }
+
+    /// Writes the response, the connection is closed after it
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            201 => "Created",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            409 => "Conflict",
+            503 => "Service Unavailable",
+            _ => "Internal Server Error",
+        };
+        write!(
+            writer,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.status,
+            reason,
+            self.content_type,
+            self.body.len()
+        )?;
+        writer.write_all(&self.body)?;
+        writer.flush()
+    }
+}
+
+/// Returns the text as a JSON string, quoted and escaped
+pub(crate) fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads a request from the text
+    fn parse(text: &str) -> io::Result<Request> {
+        read_request(&mut text.as_bytes())
+    }
+
+    /// Returns the message of the error the text gives
+    fn error(text: &str) -> String {
+        match parse(text) {
+            Ok(_) => String::from("the request was read"),
+            Err(e) => {
+                assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+                e.to_string()
+            }
+        }
+    }
+
+    #[test]
+    fn reads_the_method_the_path_and_the_body() {
+        let request =
+            parse("POST /jobs?wait=1 HTTP/1.1\r\nHost: a\r\ncontent-length: 5\r\n\r\nhello!")
+                .unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/jobs");
+        assert_eq!(request.body, b"hello");
+
+        let request = parse("GET /jobs/3 HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(request.path, "/jobs/3");
+        assert!(request.body.is_empty());
+    }
+
+    #[test]
+    fn malformed_requests_are_rejected() {
+        assert_eq!(error(""), "incomplete request");
+        assert_eq!(error("GET\r\n\r\n"), "invalid request line");
+        assert_eq!(error("GET / HTTP/1.1\r\nHost: a\r\n"), "incomplete request");
+        assert_eq!(
+            error("GET / HTTP/1.1\r\nContent-Length: ten\r\n\r\n"),
+            "invalid Content-Length"
+        );
+        assert_eq!(
+            error("POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\nabc"),
+            "incomplete body"
+        );
+    }
+
+    #[test]
+    fn bodies_over_the_limit_are_rejected() {
+        let too_long = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY + 1
+        );
+        assert_eq!(error(&too_long), "the body is too long");
+        // The longest body is accepted, and isn't allocated before it arrives
+        let longest = format!("POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY);
+        assert_eq!(error(&longest), "incomplete body");
+    }
+
+    #[test]
+    fn headers_over_the_limit_are_rejected() {
+        let headers = |count: usize| {
+            format!(
+                "GET / HTTP/1.1\r\n{}\r\n",
+                "X-Header: value\r\n".repeat(count)
+            )
+        };
+        assert!(parse(&headers(MAX_HEADERS - 1)).is_ok());
+        assert_eq!(error(&headers(MAX_HEADERS)), "too many headers");
+    }
+
+    #[test]
+    fn lines_over_the_limit_are_rejected() {
+        let long_path = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(8192));
+        assert_eq!(error(&long_path), "incomplete request");
+    }
+
+    #[test]
+    fn json_strings_are_escaped() {
+        assert_eq!(json_string("plain"), "\"plain\"");
+        assert_eq!(
+            json_string("a \"b\" c\\d\ne\u{1}"),
+            "\"a \\\"b\\\" c\\\\d\\ne\\u0001\""
+        );
+    }
+
+    #[test]
+    fn responses_have_the_status_line_and_the_length() {
+        let mut written = Vec::new();
+        Response::error(503, "busy").write_to(&mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert!(written.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+        assert!(written.contains("Content-Length: 16\r\n"));
+        assert!(written.ends_with("\r\n\r\n{\"error\":\"busy\"}"));
+    }
+}
@@ -0,0 +1,356 @@
+use crate::{
+    png::encode_png,
+    renderer::{render, CancellationToken, Progress, RenderSettings},
+    scene::Scene,
+    service::http::json_string,
+};
+use std::{
+    any::Any,
+    collections::{BTreeMap, VecDeque},
+    io,
+    panic::{self, AssertUnwindSafe},
+    rc::Rc,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Most pixels of an image a job can render, the framebuffer of a render takes about 70
+/// bytes per pixel
+const MAX_PIXELS: i64 = 1 << 22;
+
+/// Most jobs waiting to be rendered, the scenes submitted while the queue is full are refused
+const MAX_QUEUED_JOBS: usize = 16;
+
+/// Time a job is kept after it's done, cancelled or failed, then it's forgotten with its image
+const RETENTION: Duration = Duration::from_secs(3600);
+
+/// Most jobs that are done, cancelled or failed kept at the same time, the ones that ended
+/// first are forgotten to make room
+const MAX_ENDED_JOBS: usize = 64;
+
+/// Represents the state of a render job
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum JobStatus {
+    /// Waiting for the jobs before it
+    Queued,
+    /// Being rendered
+    Running,
+    /// Rendered, its image can be downloaded
+    Done,
+    /// Cancelled before it was rendered completely
+    Cancelled,
+    /// The render stopped because of an error, the job has its message
+    Failed,
+}
+
+impl JobStatus {
+    /// Returns the name of the status used in the JSON responses
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Done => "done",
+            JobStatus::Cancelled => "cancelled",
+            JobStatus::Failed => "failed",
+        }
+    }
+}
+
+/// Represents a scene submitted to be rendered
+struct Job {
+    /// The width in pixels of the image
+    width: i32,
+    /// The height in pixels of the image
+    height: i32,
+    /// The scene to render, only kept while the job is queued
+    scene: Option<Scene>,
+    /// State of the job
+    status: JobStatus,
+    /// Last progress of the render, None until it starts
+    progress: Option<Progress>,
+    /// The rendered image as a png file
+    image: Option<Vec<u8>>,
+    /// Why the job failed
+    error: Option<String>,
+    /// Stops the render when the job is cancelled
+    cancellation: CancellationToken,
+    /// Moment in which the job was done, cancelled or failed
+    ended: Option<Instant>,
+}
+
+impl Job {
+    /// Changes the status of a job that ended and frees what it no longer needs
+    fn end(&mut self, status: JobStatus) {
+        self.status = status;
+        self.scene = None;
+        self.ended = Some(Instant::now());
+    }
+}
+
+/// Represents the jobs and the order in which they are rendered
+#[derive(Default)]
+struct State {
+    /// The jobs that are queued, running or ended recently, by id
+    jobs: BTreeMap<usize, Job>,
+    /// Id of the next job submitted
+    next_id: usize,
+    /// Ids of the jobs waiting to be rendered, in order
+    queue: VecDeque<usize>,
+}
+
+impl State {
+    /// Forgets the jobs that ended more than `RETENTION` ago, and the ones that ended first
+    /// while there are more than `MAX_ENDED_JOBS`
+    fn evict(&mut self) {
+        self.jobs
+            .retain(|_, job| job.ended.is_none_or(|ended| ended.elapsed() < RETENTION));
+
+        let mut ended: Vec<(Instant, usize)> = self
+            .jobs
+            .iter()
+            .filter_map(|(&id, job)| job.ended.map(|ended| (ended, id)))
+            .collect();
+        if ended.len() > MAX_ENDED_JOBS {
+            ended.sort();
+            for (_, id) in &ended[..ended.len() - MAX_ENDED_JOBS] {
+                self.jobs.remove(id);
+            }
+        }
+    }
+}
+
+/// Represents the render jobs of the service, shared between the threads that answer the
+/// requests and the thread that renders the jobs one after the other
+#[derive(Clone, Default)]
+pub struct JobQueue {
+    /// The jobs, and the signal that wakes the render thread when a job is submitted
+    shared: Arc<(Mutex<State>, Condvar)>,
+}
+
+impl JobQueue {
+    /// Returns a queue without jobs, call `start` so they get rendered
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts the thread that renders the queued jobs, one at a time in the order they were
+    /// submitted
+    ///
+    /// A job whose render panics fails with the panic's message and the thread goes on with
+    /// the next job.
+    pub fn start(&self) -> thread::JoinHandle<()> {
+        let queue = self.clone();
+        thread::spawn(move || loop {
+            let (id, scene, cancellation) = queue.next_job();
+            queue.run(id, &scene, cancellation);
+        })
+    }
+
+    /// Waits for a queued job, marks it as running and returns it with its scene, which the
+    /// job no longer keeps
+    fn next_job(&self) -> (usize, Scene, CancellationToken) {
+        let (state, signal) = &*self.shared;
+        let mut state = state.lock().unwrap();
+        loop {
+            while let Some(id) = state.queue.pop_front() {
+                if let Some(job) = state.jobs.get_mut(&id) {
+                    if let Some(scene) = job.scene.take() {
+                        job.status = JobStatus::Running;
+                        return (id, scene, job.cancellation.clone());
+                    }
+                }
+            }
+            state = signal.wait(state).unwrap();
+        }
+    }
+
+    /// Locks the jobs, forgetting the ones that ended long ago
+    fn state(&self) -> MutexGuard<'_, State> {
+        let mut state = self.shared.0.lock().unwrap();
+        state.evict();
+        state
+    }
+
+    /// Renders a job, keeping its progress up to date, and stores its image or why it failed
+    fn run(&self, id: usize, scene: &Scene, cancellation: CancellationToken) {
+        let queue = self.clone();
+        let observer = move |progress: &Progress| {
+            if let Some(job) = queue.shared.0.lock().unwrap().jobs.get_mut(&id) {
+                job.progress = Some(*progress);
+            }
+        };
+        let settings = RenderSettings {
+            observer: Some(Rc::new(observer)),
+            cancellation: Some(cancellation.clone()),
+            ..scene.settings()
+        };
+        // The lock isn't held while rendering, so a panic doesn't poison it
+        let rendered = panic::catch_unwind(AssertUnwindSafe(|| -> io::Result<_> {
+            let framebuffer = render(&settings, &scene.camera(), &scene.world())?;
+            if cancellation.is_cancelled() {
+                return Ok(None);
+            }
+            let (width, height) = (framebuffer.width(), framebuffer.height());
+            Ok(Some(encode_png(width, height, &framebuffer.to_rgb8())))
+        }));
+
+        let mut state = self.state();
+        let job = match state.jobs.get_mut(&id) {
+            Some(job) => job,
+            None => return,
+        };
+        match rendered {
+            Ok(Ok(Some(png))) => {
+                job.image = Some(png);
+                job.end(JobStatus::Done);
+            }
+            Ok(Ok(None)) => job.end(JobStatus::Cancelled),
+            Ok(Err(e)) => {
+                job.error = Some(e.to_string());
+                job.end(JobStatus::Failed);
+            }
+            Err(panic) => {
+                job.error = Some(panic_message(panic.as_ref()));
+                job.end(JobStatus::Failed);
+            }
+        }
+    }
+
+    /// Adds a job to the end of the queue and returns its id
+    ///
+    /// A scene submitted while `MAX_QUEUED_JOBS` jobs are waiting is refused with a
+    /// `ResourceBusy` error before it's parsed.
+    ///
+    /// # Arguments
+    ///
+    /// * text - The scene in the scene file format
+    pub fn submit(&self, text: &str) -> io::Result<usize> {
+        if self.state().queue.len() >= MAX_QUEUED_JOBS {
+            return Err(queue_full());
+        }
+        let scene = Scene::parse(text)?;
+        let (width, height) = (scene.image_width as i64, scene.image_height as i64);
+        if width < 2 || height < 2 || width * height > MAX_PIXELS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the image must be at least 2x2 pixels and at most 4 megapixels",
+            ));
+        }
+
+        let mut state = self.state();
+        // Other scenes may have been queued while this one was parsed
+        if state.queue.len() >= MAX_QUEUED_JOBS {
+            return Err(queue_full());
+        }
+        let id = state.next_id;
+        state.next_id += 1;
+        state.jobs.insert(
+            id,
+            Job {
+                width: scene.image_width,
+                height: scene.image_height,
+                scene: Some(scene),
+                status: JobStatus::Queued,
+                progress: None,
+                image: None,
+                error: None,
+                cancellation: CancellationToken::new(),
+                ended: None,
+            },
+        );
+        state.queue.push_back(id);
+        self.shared.1.notify_one();
+        Ok(id)
+    }
+
+    /// Cancels a job, a running job stops at the end of its tile and has no image, returns
+    /// false if the job doesn't exist
+    pub fn cancel(&self, id: usize) -> bool {
+        let mut state = self.state();
+        let job = match state.jobs.get_mut(&id) {
+            Some(job) => job,
+            None => return false,
+        };
+        match job.status {
+            JobStatus::Queued => {
+                job.end(JobStatus::Cancelled);
+                state.queue.retain(|&queued| queued != id);
+            }
+            JobStatus::Running => job.cancellation.cancel(),
+            JobStatus::Done | JobStatus::Cancelled | JobStatus::Failed => {}
+        }
+        true
+    }
+
+    /// Returns the status of a job and its image if it's done, None if it doesn't exist
+    pub fn image(&self, id: usize) -> Option<(JobStatus, Option<Vec<u8>>)> {
+        let state = self.state();
+        let job = state.jobs.get(&id)?;
+        Some((job.status, job.image.clone()))
+    }
+
+    /// Returns the state and the progress of a job as a JSON object, None if it doesn't exist
+    pub fn status_json(&self, id: usize) -> Option<String> {
+        let state = self.state();
+        state.jobs.get(&id).map(|job| job_json(id, job))
+    }
+
+    /// Returns the state and the progress of every job that is kept as a JSON array
+    pub fn list_json(&self) -> String {
+        let state = self.state();
+        let jobs: Vec<String> = state
+            .jobs
+            .iter()
+            .map(|(&id, job)| job_json(id, job))
+            .collect();
+        format!("[{}]", jobs.join(","))
+    }
+}
+
+/// Returns the error of a scene submitted while the queue is full
+fn queue_full() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::ResourceBusy,
+        format!("{} jobs are already waiting", MAX_QUEUED_JOBS),
+    )
+}
+
+/// Returns the message a panic was started with
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+        (Some(message), _) => message.to_string(),
+        (_, Some(message)) => message.clone(),
+        _ => String::from("the render panicked"),
+    }
+}
+
+/// Returns the state and the progress of a job as a JSON object, with the error of a job that
+/// failed
+fn job_json(id: usize, job: &Job) -> String {
+    let number = |value: Option<f64>| match value {
+        Some(value) if value.is_finite() => format!("{:.3}", value),
+        _ => String::from("null"),
+    };
+    let progress = job.progress.as_ref();
+    let fraction = match job.status {
+        JobStatus::Done => Some(1.0),
+        _ => progress.map(|p| p.fraction),
+    };
+
+    format!(
+        "{{\"id\":{},\"status\":{},\"width\":{},\"height\":{},\"progress\":{},\"elapsed\":{},\
+         \"eta\":{},\"samples_per_second\":{},\"error\":{}}}",
+        id,
+        json_string(job.status.name()),
+        job.width,
+        job.height,
+        number(fraction),
+        number(progress.map(|p| p.elapsed.as_secs_f64())),
+        number(progress.and_then(|p| p.eta).map(|eta| eta.as_secs_f64())),
+        number(progress.map(|p| p.samples_per_second)),
+        job.error
+            .as_deref()
+            .map_or(String::from("null"), json_string),
+    )
+}
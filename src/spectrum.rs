@@ -0,0 +1,135 @@
+//! Helpers for spectral rendering, where each path carries a single wavelength instead of RGB
+//!
+//! RGB colors are upsampled to spectra with three smooth basis functions that add up to one, so
+//! white stays a flat spectrum and albedos stay between 0 and 1. Each sample is converted back
+//! with the CIE 1931 color matching functions, using the analytic fit by Wyman, Sloan and Shirley.
+
+use crate::{color::Color, random_float, vec3::Vec3};
+use std::sync::OnceLock;
+
+/// Shortest wavelength sampled, in nanometers
+pub const LAMBDA_MIN: f64 = 380.0;
+/// Longest wavelength sampled, in nanometers
+pub const LAMBDA_MAX: f64 = 780.0;
+
+/// Returns a wavelength in nanometers sampled uniformly in the visible range
+pub fn sample_wavelength() -> f64 {
+    random_float(LAMBDA_MIN, LAMBDA_MAX)
+}
+
+/// Returns the value at the given wavelength of the spectrum that represents a RGB color
+///
+/// # Arguments
+///
+/// * color - The RGB color to upsample
+/// * lambda - The wavelength in nanometers
+pub fn rgb_to_spectrum(color: &Color, lambda: f64) -> f64 {
+    let blue = 1.0 - smoothstep(480.0, 510.0, lambda);
+    let red = smoothstep(570.0, 600.0, lambda);
+    let green = 1.0 - blue - red;
+    color.x * red + color.y * green + color.z * blue
+}
+
+/// Returns the CIE 1931 XYZ color matching functions at the given wavelength
+///
+/// # Arguments
+///
+/// * lambda - The wavelength in nanometers
+pub fn wavelength_to_xyz(lambda: f64) -> Vec3 {
+    let g = |mu: f64, sigma1: f64, sigma2: f64| {
+        let t = (lambda - mu) / if lambda < mu { sigma1 } else { sigma2 };
+        (-0.5 * t * t).exp()
+    };
+    Vec3::new(
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    )
+}
+
+/// Converts a CIE XYZ color to linear sRGB
+pub fn xyz_to_rgb(xyz: &Vec3) -> Color {
+    Color::new(
+        3.240_454_2 * xyz.x - 1.537_138_5 * xyz.y - 0.498_531_4 * xyz.z,
+        -0.969_266_0 * xyz.x + 1.876_010_8 * xyz.y + 0.041_556_0 * xyz.z,
+        0.055_643_4 * xyz.x - 0.204_025_9 * xyz.y + 1.057_225_2 * xyz.z,
+    )
+}
+
+/// Returns the linear sRGB contribution of a sample of a uniformly sampled wavelength,
+/// balanced so that a flat spectrum of value one averages to white
+///
+/// # Arguments
+///
+/// * value - The spectral radiance carried by the sample
+/// * lambda - The wavelength of the sample in nanometers
+pub fn spectral_sample_to_rgb(value: f64, lambda: f64) -> Color {
+    static WHITE: OnceLock<Color> = OnceLock::new();
+    let white = WHITE.get_or_init(|| {
+        let mut xyz = Vec3::zero();
+        let mut lambda = LAMBDA_MIN;
+        while lambda < LAMBDA_MAX {
+            xyz += wavelength_to_xyz(lambda + 0.5);
+            lambda += 1.0;
+        }
+        xyz_to_rgb(&xyz)
+    });
+
+    let rgb = xyz_to_rgb(&(wavelength_to_xyz(lambda) * (value * (LAMBDA_MAX - LAMBDA_MIN))));
+    Color::new(rgb.x / white.x, rgb.y / white.y, rgb.z / white.z)
+}
+
+/// Returns 0 before the edge0, 1 after the edge1 and a smooth transition between them
+fn smoothstep(edge0: f64, edge1: f64, x: f64) -> f64 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flat_spectrum_averages_to_white() {
+        let samples = 400;
+        let mut sum = Color::zero();
+        for i in 0..samples {
+            let lambda = LAMBDA_MIN + (i as f64 + 0.5) * (LAMBDA_MAX - LAMBDA_MIN) / samples as f64;
+            sum += spectral_sample_to_rgb(1.0, lambda);
+        }
+        let average = sum / samples as f64;
+        assert!(
+            (average - Color::new(1.0, 1.0, 1.0)).length() < 1e-9,
+            "{:?}",
+            average
+        );
+    }
+
+    #[test]
+    fn white_is_a_flat_spectrum() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let mut lambda = LAMBDA_MIN;
+        while lambda <= LAMBDA_MAX {
+            assert!((rgb_to_spectrum(&white, lambda) - 1.0).abs() < 1e-12);
+            lambda += 5.0;
+        }
+    }
+
+    #[test]
+    fn primaries_cover_their_part_of_the_spectrum() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let green = Color::new(0.0, 1.0, 0.0);
+        let blue = Color::new(0.0, 0.0, 1.0);
+        assert_eq!(rgb_to_spectrum(&blue, 450.0), 1.0);
+        assert_eq!(rgb_to_spectrum(&green, 540.0), 1.0);
+        assert_eq!(rgb_to_spectrum(&red, 650.0), 1.0);
+        assert_eq!(rgb_to_spectrum(&red, 450.0), 0.0);
+    }
+
+    #[test]
+    fn smoothstep_goes_from_0_to_1() {
+        assert_eq!(smoothstep(1.0, 3.0, 0.0), 0.0);
+        assert_eq!(smoothstep(1.0, 3.0, 2.0), 0.5);
+        assert_eq!(smoothstep(1.0, 3.0, 4.0), 1.0);
+    }
+}
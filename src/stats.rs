@@ -0,0 +1,307 @@
+//! Statistics of the work done by a render, to profile scenes and compare machines
+//!
+//! The rays and the intersection tests are counted in thread local counters by `ray_color`
+//! and `HittableList::hit`, so counting costs almost nothing and needs no locks.
+
+use crate::renderer::CropWindow;
+use std::{cell::Cell, fmt, fs, io, ops::AddAssign, path::Path, time::Duration};
+
+thread_local! {
+    /// Work counted in the current thread since the counters were last taken
+    static COUNTERS: Cell<Counters> = Cell::new(Counters::default());
+}
+
+/// Represents the kind of a traced ray
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RayKind {
+    /// A ray cast from the camera
+    Primary,
+    /// A ray scattered by a material
+    Secondary,
+}
+
+/// Represents the work counted by the counters
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Counters {
+    /// Rays cast from the camera
+    pub primary_rays: u64,
+    /// Rays scattered by the materials
+    pub secondary_rays: u64,
+    /// Rays cast toward the lights to test if a point is in shadow, always 0 for now since the
+    /// materials only scatter and no ray samples the lights
+    pub shadow_rays: u64,
+    /// Tests of a ray against an object of a hittable list
+    pub intersection_tests: u64,
+}
+
+impl AddAssign for Counters {
+    fn add_assign(&mut self, other: Counters) {
+        self.primary_rays += other.primary_rays;
+        self.secondary_rays += other.secondary_rays;
+        self.shadow_rays += other.shadow_rays;
+        self.intersection_tests += other.intersection_tests;
+    }
+}
+
+/// Counts a ray traced in the current thread
+pub(crate) fn count_ray(kind: RayKind) {
+    COUNTERS.with(|counters| {
+        let mut c = counters.get();
+        match kind {
+            RayKind::Primary => c.primary_rays += 1,
+            RayKind::Secondary => c.secondary_rays += 1,
+        }
+        counters.set(c);
+    });
+}
+
+/// Counts an intersection test made in the current thread
+pub(crate) fn count_intersection_test() {
+    COUNTERS.with(|counters| {
+        let mut c = counters.get();
+        c.intersection_tests += 1;
+        counters.set(c);
+    });
+}
+
+/// Returns the work counted in the current thread and sets its counters back to zero
+pub fn take_counters() -> Counters {
+    COUNTERS.with(|counters| counters.replace(Counters::default()))
+}
+
+/// Represents the work done in a tile of the image
+#[derive(Clone, Copy, Debug)]
+pub struct TileStats {
+    /// The pixels of the tile, in pixels of the full image
+    pub window: CropWindow,
+    /// Time spent rendering the tile
+    pub time: Duration,
+    /// Samples cast in the tile
+    pub samples: u64,
+}
+
+/// Represents the statistics of a render
+#[derive(Clone, Debug, Default)]
+pub struct RenderStats {
+    /// The rays and the intersection tests of the render
+    pub counters: Counters,
+    /// Time the render took
+    pub time: Duration,
+    /// The work done in each tile, from the top left to the bottom right corner
+    pub tiles: Vec<TileStats>,
+}
+
+impl RenderStats {
+    /// Returns the number of rays of every kind
+    pub fn total_rays(&self) -> u64 {
+        self.counters.primary_rays + self.counters.secondary_rays + self.counters.shadow_rays
+    }
+
+    /// Returns the average number of intersection tests of a ray
+    pub fn intersection_tests_per_ray(&self) -> f64 {
+        self.counters.intersection_tests as f64 / self.total_rays().max(1) as f64
+    }
+
+    /// Returns the average number of rays of a path, from the camera to the sky or until it's
+    /// absorbed
+    pub fn average_path_length(&self) -> f64 {
+        let path_rays = self.counters.primary_rays + self.counters.secondary_rays;
+        path_rays as f64 / self.counters.primary_rays.max(1) as f64
+    }
+
+    /// Returns the millions of rays traced per second
+    pub fn mrays_per_second(&self) -> f64 {
+        self.total_rays() as f64 / self.time.as_secs_f64().max(1e-9) / 1e6
+    }
+
+    /// Returns the shortest, the average and the longest time spent in a tile
+    pub fn tile_times(&self) -> (Duration, Duration, Duration) {
+        let times = self.tiles.iter().map(|tile| tile.time);
+        let total: Duration = times.clone().sum();
+        (
+            times.clone().min().unwrap_or_default(),
+            total / self.tiles.len().max(1) as u32,
+            times.max().unwrap_or_default(),
+        )
+    }
+
+    /// Returns the statistics as a JSON object
+    pub fn to_json(&self) -> String {
+        let tiles: Vec<String> = self
+            .tiles
+            .iter()
+            .map(|tile| {
+                format!(
+                    "{{\"x\":{},\"y\":{},\"width\":{},\"height\":{},\"time\":{:.6},\"samples\":{}}}",
+                    tile.window.x,
+                    tile.window.y,
+                    tile.window.width,
+                    tile.window.height,
+                    tile.time.as_secs_f64(),
+                    tile.samples
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\"time\":{:.6},\"rays\":{{\"total\":{},\"primary\":{},\"secondary\":{},\"shadow\":{}}},\
+             \"intersection_tests\":{},\"intersection_tests_per_ray\":{:.3},\
+             \"average_path_length\":{:.3},\"mrays_per_second\":{:.3},\"tiles\":[{}]}}",
+            self.time.as_secs_f64(),
+            self.total_rays(),
+            self.counters.primary_rays,
+            self.counters.secondary_rays,
+            self.counters.shadow_rays,
+            self.counters.intersection_tests,
+            self.intersection_tests_per_ray(),
+            self.average_path_length(),
+            self.mrays_per_second(),
+            tiles.join(",")
+        )
+    }
+
+    /// Writes the statistics to a JSON file
+    ///
+    /// # Arguments
+    ///
+    /// * path - Path of the JSON file
+    pub fn write_json(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_json())
+    }
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (shortest, average, longest) = self.tile_times();
+        writeln!(f, "Time: {:.2?}", self.time)?;
+        writeln!(
+            f,
+            "Rays: {} ({} primary, {} secondary, {} shadow)",
+            self.total_rays(),
+            self.counters.primary_rays,
+            self.counters.secondary_rays,
+            self.counters.shadow_rays
+        )?;
+        writeln!(
+            f,
+            "Intersection tests: {} ({:.2} per ray)",
+            self.counters.intersection_tests,
+            self.intersection_tests_per_ray()
+        )?;
+        writeln!(f, "Average path length: {:.2}", self.average_path_length())?;
+        writeln!(
+            f,
+            "Time per tile: {:.2?} shortest, {:.2?} average, {:.2?} longest ({} tiles)",
+            shortest,
+            average,
+            longest,
+            self.tiles.len()
+        )?;
+        write!(f, "Speed: {:.2} Mrays/s", self.mrays_per_second())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the statistics of a render with the given counters and tile times in
+    /// milliseconds
+    fn stats(counters: Counters, tile_times: &[u64]) -> RenderStats {
+        RenderStats {
+            counters,
+            time: Duration::from_secs(2),
+            tiles: tile_times
+                .iter()
+                .map(|&time| TileStats {
+                    window: CropWindow::new(0, 0, 32, 32),
+                    time: Duration::from_millis(time),
+                    samples: 1024,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn rays_and_tests_are_counted_until_taken() {
+        take_counters();
+        count_ray(RayKind::Primary);
+        count_ray(RayKind::Secondary);
+        count_ray(RayKind::Secondary);
+        count_intersection_test();
+        let counters = take_counters();
+        assert_eq!(counters.primary_rays, 1);
+        assert_eq!(counters.secondary_rays, 2);
+        assert_eq!(counters.shadow_rays, 0);
+        assert_eq!(counters.intersection_tests, 1);
+
+        let counters = take_counters();
+        assert_eq!(counters.primary_rays + counters.secondary_rays, 0);
+    }
+
+    #[test]
+    fn counters_of_threads_add_up() {
+        let mut total = Counters {
+            primary_rays: 1,
+            secondary_rays: 2,
+            shadow_rays: 3,
+            intersection_tests: 4,
+        };
+        total += total;
+        assert_eq!(
+            (total.primary_rays, total.secondary_rays, total.shadow_rays),
+            (2, 4, 6)
+        );
+        assert_eq!(total.intersection_tests, 8);
+    }
+
+    #[test]
+    fn derived_statistics() {
+        let counters = Counters {
+            primary_rays: 100,
+            secondary_rays: 150,
+            shadow_rays: 50,
+            intersection_tests: 1200,
+        };
+        let stats = stats(counters, &[30, 10, 20]);
+        assert_eq!(stats.total_rays(), 300);
+        assert_eq!(stats.intersection_tests_per_ray(), 4.0);
+        assert_eq!(stats.average_path_length(), 2.5);
+        assert!((stats.mrays_per_second() - 0.00015).abs() < 1e-12);
+        assert_eq!(
+            stats.tile_times(),
+            (
+                Duration::from_millis(10),
+                Duration::from_millis(20),
+                Duration::from_millis(30)
+            )
+        );
+    }
+
+    #[test]
+    fn a_render_without_rays_or_tiles_has_finite_statistics() {
+        let stats = stats(Counters::default(), &[]);
+        assert_eq!(stats.intersection_tests_per_ray(), 0.0);
+        assert_eq!(stats.average_path_length(), 0.0);
+        assert_eq!(stats.tile_times().1, Duration::default());
+    }
+
+    #[test]
+    fn report_and_json_have_every_ray_kind() {
+        let counters = Counters {
+            primary_rays: 1,
+            secondary_rays: 2,
+            shadow_rays: 0,
+            intersection_tests: 3,
+        };
+        let stats = stats(counters, &[5]);
+        assert!(stats
+            .to_string()
+            .contains("Rays: 3 (1 primary, 2 secondary, 0 shadow)"));
+        let json = stats.to_json();
+        assert!(json.contains("\"rays\":{\"total\":3,\"primary\":1,\"secondary\":2,\"shadow\":0}"));
+        assert!(json.contains(
+            "\"tiles\":[{\"x\":0,\"y\":0,\"width\":32,\"height\":32,\"time\":0.005000,\"samples\":1024}]"
+        ));
+    }
+}
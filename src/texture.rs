@@ -0,0 +1,59 @@
+mod checker;
+mod image;
+
+pub use {checker::CheckerTexture, image::ImageTexture};
+
+use crate::{color::Color, vec3::Point3};
+
+/// Represents a color, or a scalar value, that varies along the surface of an object
+///
+/// A plain `Color` or `f64` can be used wherever a texture is expected, in which case the
+/// texture has the same value everywhere. Scalar parameters read the first (red) channel.
+pub trait Texture {
+    /// Returns the value of the texture at the given surface coordinates
+    ///
+    /// # Arguments
+    ///
+    /// * u - horizontal surface coordinate, between 0 and 1
+    /// * v - vertical surface coordinate, between 0 and 1
+    /// * p - point in space where the texture is sampled
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
+}
+
+impl Texture for Color {
+    fn value(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        *self
+    }
+}
+
+impl Texture for f64 {
+    fn value(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        Color::new(*self, *self, *self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constants_are_the_same_everywhere() {
+        let p = Point3::new(1.0, -2.0, 3.0);
+        let color = Color::new(0.1, 0.2, 0.3);
+        assert_eq!(color.value(0.0, 0.0, &p), color);
+        assert_eq!(color.value(0.7, 0.2, &Point3::zero()), color);
+        assert_eq!(0.4.value(0.5, 0.5, &p), Color::new(0.4, 0.4, 0.4));
+    }
+
+    #[test]
+    fn checker_alternates_between_its_textures() {
+        let checker = CheckerTexture::new(1.0, 0.0, 1.0);
+        let even = Point3::new(1.0, 1.0, 1.0);
+        let odd = Point3::new(-1.0, 1.0, 1.0);
+        assert_eq!(checker.value(0.0, 0.0, &even), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(checker.value(0.0, 0.0, &odd), Color::zero());
+        // A higher frequency makes smaller cells
+        let fine = CheckerTexture::new(1.0, 0.0, 4.0);
+        assert_eq!(fine.value(0.0, 0.0, &even), Color::zero());
+    }
+}
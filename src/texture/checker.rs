@@ -0,0 +1,42 @@
+use crate::{color::Color, texture::Texture, vec3::Point3};
+use std::rc::Rc;
+
+/// Represents a 3D checker pattern that alternates between two textures
+pub struct CheckerTexture {
+    /// Texture used in the even cells
+    even: Rc<dyn Texture>,
+    /// Texture used in the odd cells
+    odd: Rc<dyn Texture>,
+    /// Number of cells per unit of space
+    frequency: f64,
+}
+
+impl CheckerTexture {
+    /// Returns a checker texture
+    ///
+    /// # Arguments
+    ///
+    /// * even - texture of the even cells
+    /// * odd - texture of the odd cells
+    /// * frequency - number of cells per unit of space
+    pub fn new(even: impl Texture + 'static, odd: impl Texture + 'static, frequency: f64) -> Self {
+        Self {
+            even: Rc::new(even),
+            odd: Rc::new(odd),
+            frequency,
+        }
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let sines = (self.frequency * p.x).sin()
+            * (self.frequency * p.y).sin()
+            * (self.frequency * p.z).sin();
+        if sines < 0.0 {
+            self.odd.value(u, v, p)
+        } else {
+            self.even.value(u, v, p)
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{
+    clamp,
+    color::{read_ppm, Color},
+    texture::Texture,
+    vec3::Point3,
+};
+use std::{io, path::Path};
+
+/// Represents a texture read from a PPM image that is mapped with the surface coordinates
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    /// Pixels from the top left to the bottom right corner
+    pixels: Vec<Color>,
+}
+
+impl ImageTexture {
+    /// Reads a color image, converting it from the gamma used by `write_color` to linear values
+    ///
+    /// # Arguments
+    ///
+    /// * path - path of the PPM file (P3 or P6)
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut texture = Self::open_linear(path)?;
+        for pixel in texture.pixels.iter_mut() {
+            *pixel = *pixel * *pixel;
+        }
+        Ok(texture)
+    }
+
+    /// Reads an image that holds data instead of colors (roughness, normals, masks...) without
+    /// changing its values
+    ///
+    /// # Arguments
+    ///
+    /// * path - path of the PPM file (P3 or P6)
+    pub fn open_linear(path: impl AsRef<Path>) -> io::Result<Self> {
+        let (width, height, pixels) = read_ppm(path.as_ref())?;
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        if self.pixels.is_empty() {
+            return Color::new(0.0, 1.0, 1.0);
+        }
+
+        let u = clamp(u, 0.0, 1.0);
+        let v = 1.0 - clamp(v, 0.0, 1.0);
+
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+
+        self.pixels[j * self.width + i]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, path::PathBuf, process};
+
+    /// Writes a 2x2 image whose top left pixel is red, top right green, bottom left blue and
+    /// bottom right white, returns its path
+    fn write_image(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("ray_tracer_{}_{}", process::id(), name));
+        fs::write(
+            &path,
+            "P3\n2 2\n255\n255 0 0  0 255 0\n0 0 255  255 255 255\n",
+        )
+        .unwrap();
+        path
+    }
+
+    #[test]
+    fn surface_coordinates_map_to_the_pixels() {
+        let path = write_image("texture_map.ppm");
+        let texture = ImageTexture::open_linear(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let p = Point3::zero();
+        assert_eq!(texture.value(0.0, 1.0, &p), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(texture.value(0.9, 0.9, &p), Color::new(0.0, 1.0, 0.0));
+        assert_eq!(texture.value(0.1, 0.1, &p), Color::new(0.0, 0.0, 1.0));
+        assert_eq!(texture.value(1.0, 0.0, &p), Color::new(1.0, 1.0, 1.0));
+        // Coordinates outside the image are clamped to its edges
+        assert_eq!(texture.value(-3.0, 5.0, &p), Color::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn color_images_are_made_linear() {
+        let path = write_image("texture_gamma.ppm");
+        fs::write(&path, "P3\n1 1\n10\n5 10 0\n").unwrap();
+        let color = ImageTexture::open(&path).unwrap();
+        let data = ImageTexture::open_linear(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let p = Point3::zero();
+        assert_eq!(color.value(0.5, 0.5, &p), Color::new(0.25, 1.0, 0.0));
+        assert_eq!(data.value(0.5, 0.5, &p), Color::new(0.5, 1.0, 0.0));
+    }
+}
@@ -3,7 +3,7 @@ use std::ops::{Add, AddAssign, Div, DivAssign, Index, Mul, MulAssign, Neg, Sub};
 
 /// Represents a vector with 3 dimensions
 /// A Vec3 contains the 3 cordinates (x, y, z) as f64
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Vec3 {
     pub x: f64,
     pub y: f64,
@@ -73,10 +73,24 @@ impl Vec3 {
         v - &(normal * Vec3::dot(v, normal) * 2.0)
     }
 
+    /// Returns the refractod vector from the incidence of the incoming vector agains a material
+    ///
+    /// # Arguments
+    ///
+    /// * uv - The vector that represents the direction of the incoming ray
+    /// * n - The normal of the plane hitted
+    /// * etai_over_etat - Ratio between the refraction index of the material the ray was traveling through and the material of the hitted object
+    pub fn refract(uv: &Vec3, n: &Vec3, etai_over_etat: f64) -> Vec3 {
+        let cos_theta = Vec3::dot(&(-uv), n).min(1.0);
+        let r_out_perp: Vec3 = (uv + &(n * cos_theta)) * etai_over_etat;
+        let r_out_parallel: Vec3 = n * -(1.0 - r_out_perp.lenght_squared()).abs().sqrt();
+        r_out_parallel + r_out_perp
+    }
+
     /// Returns the normalized vector
     pub fn unit_vector(v: &Vec3) -> Vec3 {
-        let vec = v.clone();
-        return vec / vec.length();
+        let vec = *v;
+        vec / vec.length()
     }
 
     /// Returns the length squared of a vector
@@ -174,7 +188,7 @@ impl Add for Vec3 {
     }
 }
 
-impl<'a, 'b> Add<&'b Vec3> for &'b Vec3 {
+impl<'b> Add<&'b Vec3> for &'b Vec3 {
     type Output = Vec3;
 
     fn add(self, other: &'b Vec3) -> Self::Output {
@@ -198,7 +212,7 @@ impl Sub for Vec3 {
     }
 }
 
-impl<'a, 'b> Sub<&'b Vec3> for &'a Vec3 {
+impl<'b> Sub<&'b Vec3> for &Vec3 {
     type Output = Vec3;
 
     fn sub(self, other: &'b Vec3) -> Self::Output {
@@ -234,7 +248,7 @@ impl Mul<f64> for Vec3 {
     }
 }
 
-impl<'a> Mul<f64> for &'a Vec3 {
+impl Mul<f64> for &Vec3 {
     type Output = Vec3;
 
     fn mul(self, rhs: f64) -> Self::Output {
@@ -266,7 +280,7 @@ impl Neg for Vec3 {
     }
 }
 
-impl<'a> Neg for &'a Vec3 {
+impl Neg for &Vec3 {
     type Output = Vec3;
 
     fn neg(self) -> Self::Output {
@@ -0,0 +1,128 @@
+mod common;
+
+use common::{samples, scene};
+use ray_tracer::{
+    filter::{Filter, FilterKind},
+    renderer::{
+        render, AdaptiveSampling, CancellationToken, Checkpoint, Progress, RenderSettings,
+        StopCondition,
+    },
+    scene::Scene,
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Duration,
+};
+
+/// Returns a path in the temporary directory that no other test uses
+fn temporary_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("ray_tracer_{}_{}", std::process::id(), name));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+/// Returns the settings of a small render of the test scene that saves to the checkpoint
+fn settings(scene: &Scene, checkpoint: &Path) -> RenderSettings {
+    RenderSettings {
+        image_width: 48,
+        image_height: 27,
+        stop: StopCondition::Samples(6),
+        observer: None,
+        checkpoint: Some(Checkpoint::new(checkpoint, Duration::from_secs(3600))),
+        ..scene.settings()
+    }
+}
+
+/// Renders with the settings, cancelling the render after its first tile and then resuming
+/// it, and checks the result is the same as a render that wasn't interrupted
+fn check_resume(make_settings: impl Fn(&Path) -> RenderSettings, name: &str) {
+    let scene = scene();
+    let (camera, world) = (scene.camera(), scene.world());
+    let path = temporary_path(name);
+    let uninterrupted_settings = RenderSettings {
+        checkpoint: None,
+        ..make_settings(&path)
+    };
+    let uninterrupted = render(&uninterrupted_settings, &camera, &world).unwrap();
+
+    let cancellation = CancellationToken::new();
+    let token = cancellation.clone();
+    let interrupted_settings = RenderSettings {
+        observer: Some(Rc::new(move |_: &Progress| token.cancel())),
+        cancellation: Some(cancellation),
+        ..make_settings(&path)
+    };
+    let interrupted = render(&interrupted_settings, &camera, &world).unwrap();
+    assert!(interrupted.total_samples() < uninterrupted.total_samples());
+
+    let resumed = render(&make_settings(&path), &camera, &world).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(samples(&resumed) == samples(&uninterrupted));
+}
+
+#[test]
+fn resumed_render_matches_uninterrupted_render() {
+    let scene = scene();
+    check_resume(|path| settings(&scene, path), "resume.ckpt");
+}
+
+#[test]
+fn resumed_adaptive_render_matches_uninterrupted_render() {
+    let scene = scene();
+    check_resume(
+        |path| RenderSettings {
+            stop: StopCondition::Samples(12),
+            adaptive: Some(AdaptiveSampling {
+                min_samples: 2,
+                max_samples: 12,
+                noise_threshold: 0.2,
+            }),
+            ..settings(&scene, path)
+        },
+        "resume_adaptive.ckpt",
+    );
+}
+
+#[test]
+fn resuming_with_other_settings_is_refused() {
+    let scene = scene();
+    let (camera, world) = (scene.camera(), scene.world());
+    let path = temporary_path("other_settings.ckpt");
+    render(&settings(&scene, &path), &camera, &world).unwrap();
+
+    let others = [
+        RenderSettings {
+            image_width: 40,
+            ..settings(&scene, &path)
+        },
+        RenderSettings {
+            max_depth: 10,
+            ..settings(&scene, &path)
+        },
+        RenderSettings {
+            stop: StopCondition::Samples(7),
+            ..settings(&scene, &path)
+        },
+        RenderSettings {
+            filter: Filter::new(FilterKind::Gaussian, 1.5).unwrap(),
+            ..settings(&scene, &path)
+        },
+    ];
+    for other in &others {
+        match render(other, &camera, &world) {
+            Err(e) => {
+                assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+                assert!(
+                    e.to_string()
+                        .contains("was saved by a render with other settings"),
+                    "{}",
+                    e
+                );
+            }
+            Ok(_) => panic!("the render resumed"),
+        }
+    }
+    fs::remove_file(&path).unwrap();
+}
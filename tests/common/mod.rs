@@ -0,0 +1,45 @@
+//! Helpers shared by the integration tests, each test file uses some of them
+#![allow(dead_code)]
+
+use ray_tracer::{
+    distributed::serve,
+    framebuffer::Framebuffer,
+    renderer::{render, CropWindow, RenderSettings},
+    scene::Scene,
+};
+use std::{net::TcpListener, path::Path, thread};
+
+/// Returns a small version of the test scene
+pub fn scene() -> Scene {
+    Scene {
+        image_width: 64,
+        image_height: 36,
+        samples_per_pixel: 4,
+        ..Scene::open(Path::new("scenes/three_spheres.scene")).unwrap()
+    }
+}
+
+/// Starts a worker on a free localhost port and returns its address
+pub fn start_worker() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || serve(&listener));
+    address
+}
+
+/// Returns every value the framebuffer keeps, to compare framebuffers bit for bit
+pub fn samples(framebuffer: &Framebuffer) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    framebuffer.write_samples(&mut bytes).unwrap();
+    bytes
+}
+
+/// Renders the scene on this machine, only the crop window if there is one
+pub fn render_locally(scene: &Scene, crop: Option<CropWindow>) -> Framebuffer {
+    let settings = RenderSettings {
+        observer: None,
+        crop,
+        ..scene.settings()
+    };
+    render(&settings, &scene.camera(), &scene.world()).unwrap()
+}
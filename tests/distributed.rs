@@ -0,0 +1,72 @@
+mod common;
+
+use common::{render_locally, samples, scene, start_worker};
+use ray_tracer::{
+    distributed::Coordinator,
+    renderer::{Progress, ProgressObserver},
+};
+use std::{
+    cell::Cell,
+    io::{self, Write},
+    net::TcpListener,
+    rc::Rc,
+    thread,
+    time::Duration,
+};
+
+/// Keeps the fraction of the render that was done when it finished
+struct Finished(Rc<Cell<Option<f64>>>);
+
+impl ProgressObserver for Finished {
+    fn progress(&self, _: &Progress) {}
+
+    fn finished(&self, progress: &Progress) {
+        self.0.set(Some(progress.fraction));
+    }
+}
+
+#[test]
+fn distributed_render_matches_local_render() {
+    let scene = scene();
+    let finished = Rc::new(Cell::new(None));
+    let coordinator = Coordinator::new(vec![start_worker(), start_worker()])
+        .with_tile_size(16)
+        .with_observer(Rc::new(Finished(finished.clone())));
+    let (distributed, failures) = coordinator.render(&scene).unwrap();
+    assert!(samples(&distributed) == samples(&render_locally(&scene, None)));
+    assert!(failures.is_empty());
+    assert_eq!(finished.get(), Some(1.0));
+}
+
+#[test]
+fn tiles_of_a_hung_worker_go_to_the_others() {
+    let scene = scene();
+    // Connections to a listener that never accepts them are never answered
+    let hung = TcpListener::bind("127.0.0.1:0").unwrap();
+    let hung_address = hung.local_addr().unwrap().to_string();
+    let workers = vec![hung_address.clone(), start_worker()];
+    let coordinator = Coordinator::new(workers)
+        .with_tile_size(16)
+        .with_timeout(Duration::from_secs(1));
+    let (distributed, failures) = coordinator.render(&scene).unwrap();
+    assert!(samples(&distributed) == samples(&render_locally(&scene, None)));
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].address, hung_address);
+}
+
+#[test]
+fn samples_of_the_wrong_size_are_rejected() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        stream.write_all(&u32::MAX.to_le_bytes()).unwrap();
+        stream.write_all(&u32::MAX.to_le_bytes()).unwrap();
+        io::copy(&mut stream, &mut io::sink())
+    });
+
+    match Coordinator::new(vec![address]).render(&scene()) {
+        Err(e) => assert_eq!(e.kind(), io::ErrorKind::InvalidData),
+        Ok(_) => panic!("the samples of the wrong size were merged"),
+    }
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use common::{render_locally, scene, start_worker};
+use ray_tracer::{
+    distributed::Coordinator,
+    filter::{Filter, FilterKind},
+    framebuffer::Framebuffer,
+    renderer::CropWindow,
+    scene::Scene,
+};
+
+/// Returns a small version of the test scene with a gaussian filter
+fn filtered_scene() -> Scene {
+    Scene {
+        filter: Filter::new(FilterKind::Gaussian, 1.5).unwrap(),
+        ..scene()
+    }
+}
+
+/// Checks that the pixels of the framebuffer are the pixels of the full image whose top left
+/// corner is at the given column and row
+fn assert_same_pixels(part: &Framebuffer, full: &Framebuffer, x: usize, y: usize) {
+    for j in 0..part.height() {
+        for i in 0..part.width() {
+            let (expected, actual) = (full.color(x + i, y + j), part.color(i, j));
+            assert_eq!(
+                [expected.x, expected.y, expected.z].map(f64::to_bits),
+                [actual.x, actual.y, actual.z].map(f64::to_bits),
+                "pixel {},{}",
+                x + i,
+                y + j
+            );
+            assert_eq!(full.samples(x + i, y + j), part.samples(i, j));
+        }
+    }
+}
+
+#[test]
+fn filtered_crop_matches_full_render() {
+    let scene = filtered_scene();
+    let full = render_locally(&scene, None);
+    let crop = CropWindow::new(10, 5, 37, 21);
+    let part = render_locally(&scene, Some(crop));
+    assert_same_pixels(&part, &full, crop.x, crop.y);
+}
+
+#[test]
+fn filtered_distributed_render_matches_full_render() {
+    let scene = filtered_scene();
+    let coordinator = Coordinator::new(vec![start_worker()]).with_tile_size(16);
+    let distributed = coordinator.render(&scene).unwrap().0;
+    assert_same_pixels(&distributed, &render_locally(&scene, None), 0, 0);
+}
+
+#[test]
+fn invalid_radii_are_rejected() {
+    for radius in [0.0, -1.0, 8.5, f64::INFINITY, f64::NAN] {
+        assert!(Filter::new(FilterKind::Tent, radius).is_err());
+    }
+    assert!(Scene::parse("filter tent inf\n").is_err());
+    assert!(Scene::parse("filter gaussian 1e400\n").is_err());
+    assert!(Scene::parse("filter mitchell 8\n").is_ok());
+}